@@ -0,0 +1,492 @@
+//! Async counterparts of [`ByteRead`][`crate::io::ByteRead`], [`ByteWrite`][`crate::io::ByteWrite`], [`ByteDeserialize`] and [`ByteSerialize`].
+//!
+//! Encoders don't need to know much about async: one that implements [`BufferedAsyncDeserialize`] is also an [`AsyncByteDeserialize`], which decodes out of the reader's buffer with [`decode_prefix`] and reads more input whenever that runs out. Encoders of values that can grow large item by item implement [`AsyncByteDeserialize`] themselves instead, as [`LengthPrefixList`] does, so that what has been decoded isn't decoded again. Every [`ByteSerialize`] serializes into a buffer which is then written out in one go.
+
+use crate::block_list::BlockList;
+use crate::byte_length::ByteLengthPrefixed;
+use crate::const_list::ConstListEncoder;
+use crate::envelope::Envelope;
+use crate::io::EndOfBufferError;
+use crate::incremental::{decode_prefix, Decoded};
+use crate::lists::{prealloc_len, LengthPrefixList, ListParseError};
+use crate::serde::{ByteConstDeserialize, ByteConstSize, ByteDeserialize, ByteSerialize, ByteTypeId, ParseOrIOError};
+use crate::string::LengthPrefixString;
+use crate::varint::{SVarInt, UVarInt};
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncByteRead {
+    type Err: From<EndOfBufferError>;
+
+    /// The bytes that have been read in but not consumed yet
+    fn buffer(&self) -> &[u8];
+
+    /// Marks the first `len` bytes of [`AsyncByteRead::buffer`] as consumed
+    fn consume(&mut self, len: usize);
+
+    /// Reads more input onto the end of [`AsyncByteRead::buffer`], returning how many bytes were added. Returns 0 only at the end of the input.
+    async fn fill_more(&mut self) -> Result<usize, Self::Err>;
+
+    async fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        while self.buffer().is_empty() {
+            if self.fill_more().await? == 0 {
+                return Err(EndOfBufferError.into());
+            }
+        }
+        let res = self.buffer()[0];
+        self.consume(1);
+        Ok(res)
+    }
+
+    async fn read_buf(&mut self, len: usize) -> Result<Vec<u8>, Self::Err> {
+        while self.buffer().len() < len {
+            if self.fill_more().await? == 0 {
+                return Err(EndOfBufferError.into());
+            }
+        }
+        let res = self.buffer()[..len].to_vec();
+        self.consume(len);
+        Ok(res)
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncByteWrite {
+    type Err;
+
+    async fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err>;
+
+    async fn flush(&mut self) -> Result<(), Self::Err>;
+}
+
+impl<R: AsyncByteRead + ?Sized> AsyncByteRead for &mut R {
+    type Err = R::Err;
+
+    fn buffer(&self) -> &[u8] {
+        (**self).buffer()
+    }
+
+    fn consume(&mut self, len: usize) {
+        (**self).consume(len)
+    }
+
+    async fn fill_more(&mut self) -> Result<usize, Self::Err> {
+        (**self).fill_more().await
+    }
+}
+
+impl<W: AsyncByteWrite + ?Sized> AsyncByteWrite for &mut W {
+    type Err = W::Err;
+
+    async fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        (**self).write_buf(data).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Err> {
+        (**self).flush().await
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncByteDeserialize<T> : ByteDeserialize<T> {
+    async fn byte_deserialize_async<R: AsyncByteRead>(io: &mut R) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>>;
+}
+
+/// Opts an encoder into the [`AsyncByteDeserialize`] impl that buffers the whole value and decodes it with [`decode_prefix`].
+///
+/// Each attempt decodes the value from the start of the buffer, and the next attempt waits until at least as many more bytes as it came up short have arrived. That's cheap for values whose size is known after reading a few bytes, like numbers and strings, but a value that is only ever found to be short by a little at a time, like a long list of small items trickling in, is decoded over again for every piece, which is quadratic in its size.
+pub trait BufferedAsyncDeserialize<T> : ByteDeserialize<T> {}
+
+impl<P, T> BufferedAsyncDeserialize<T> for P
+where P: ByteConstDeserialize<T>, [(); <P as ByteConstSize<T>>::BYTE_SIZE]: {}
+
+impl BufferedAsyncDeserialize<u64> for UVarInt {}
+impl BufferedAsyncDeserialize<i64> for SVarInt {}
+impl<LE: ByteTypeId<u64>> BufferedAsyncDeserialize<String> for LengthPrefixString<LE> where Self: ByteDeserialize<String> {}
+impl<VE, V, const N: usize> BufferedAsyncDeserialize<[V; N]> for ConstListEncoder<VE> where Self: ByteDeserialize<[V; N]> {}
+impl<LE, VE, B, V> BufferedAsyncDeserialize<Vec<V>> for BlockList<LE, VE, B> where Self: ByteDeserialize<Vec<V>> {}
+impl<LE, E, V> BufferedAsyncDeserialize<Vec<V>> for ByteLengthPrefixed<LE, E> where Self: ByteDeserialize<Vec<V>> {}
+impl<LE, E> BufferedAsyncDeserialize<String> for ByteLengthPrefixed<LE, E> where Self: ByteDeserialize<String> {}
+impl<E, H, S, V> BufferedAsyncDeserialize<Vec<V>> for Envelope<E, H, S> where Self: ByteDeserialize<Vec<V>> {}
+impl<E, H, S> BufferedAsyncDeserialize<String> for Envelope<E, H, S> where Self: ByteDeserialize<String> {}
+
+impl<E, T> AsyncByteDeserialize<T> for E
+where
+    E: BufferedAsyncDeserialize<T>,
+{
+    async fn byte_deserialize_async<R: AsyncByteRead>(io: &mut R) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>> {
+        loop {
+            match decode_prefix::<E, T>(io.buffer()).map_err(ParseOrIOError::Parse)? {
+                Decoded::Complete{value, consumed} => {
+                    io.consume(consumed);
+                    return Ok(value);
+                },
+                Decoded::Incomplete{needed} => {
+                    let target = io.buffer().len() + needed;
+                    while io.buffer().len() < target {
+                        if io.fill_more().await.map_err(ParseOrIOError::IO)? == 0 {
+                            return Err(ParseOrIOError::IO(EndOfBufferError.into()));
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Decodes one item at a time, so only the item being decoded is ever decoded again as more input arrives.
+impl<LE, VE, V> AsyncByteDeserialize<Vec<V>> for LengthPrefixList<LE, VE>
+where
+    LE: AsyncByteDeserialize<u64>,
+    VE: AsyncByteDeserialize<V>,
+{
+    async fn byte_deserialize_async<R: AsyncByteRead>(io: &mut R) -> Result<Vec<V>, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let length:u64 = LE::byte_deserialize_async(io).await.map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+
+        let mut res = Vec::with_capacity(prealloc_len::<V>(length));
+        for idx in 0..length {
+            res.push(VE::byte_deserialize_async(io).await.map_err(|e| e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}))?);
+        }
+        Ok(res)
+    }
+}
+
+#[allow(async_fn_in_trait)]
+pub trait AsyncByteSerialize<T: ?Sized> : ByteSerialize<T> {
+    async fn byte_serialize_async<W: AsyncByteWrite>(item: &T, io: &mut W) -> Result<(), W::Err>;
+}
+
+impl<E, T> AsyncByteSerialize<T> for E
+where
+    E: ByteSerialize<T>,
+    T: ?Sized,
+{
+    async fn byte_serialize_async<W: AsyncByteWrite>(item: &T, io: &mut W) -> Result<(), W::Err> {
+        let mut buf = Vec::new();
+        E::byte_serialize(item, &mut buf).unwrap();
+        io.write_buf(&buf).await
+    }
+}
+
+/// Drops the consumed bytes from the front of `buf` and makes room for a large read, so one [`AsyncByteRead::fill_more`] can take in everything that has arrived.
+#[cfg(any(feature = "tokio", feature = "futures"))]
+fn reserve_more(buf: &mut Vec<u8>, pos: &mut usize) {
+    if *pos > 0 {
+        buf.drain(..*pos);
+        *pos = 0;
+    }
+    buf.reserve(buf.len().max(8 * 1024));
+}
+
+/// Adapts a [`tokio::io::AsyncRead`] and/or [`tokio::io::AsyncWrite`] into an [`AsyncByteRead`]/[`AsyncByteWrite`].
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioWrapper<V> {
+    inner: V,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "tokio")]
+impl<V> TokioWrapper<V> {
+    pub fn new(inner: V) -> Self {
+        Self{inner, buf: Vec::new(), pos: 0}
+    }
+
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    /// Any buffered but unconsumed read data is lost.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<V: tokio::io::AsyncRead + Unpin> AsyncByteRead for TokioWrapper<V> {
+    type Err = std::io::Error;
+
+    fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.pos += len;
+        assert!(self.pos <= self.buf.len());
+    }
+
+    async fn fill_more(&mut self) -> Result<usize, Self::Err> {
+        use tokio::io::AsyncReadExt;
+
+        reserve_more(&mut self.buf, &mut self.pos);
+        self.inner.read_buf(&mut self.buf).await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<V: tokio::io::AsyncWrite + Unpin> AsyncByteWrite for TokioWrapper<V> {
+    type Err = std::io::Error;
+
+    async fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        use tokio::io::AsyncWriteExt;
+
+        self.inner.write_all(data).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Err> {
+        use tokio::io::AsyncWriteExt;
+
+        self.inner.flush().await
+    }
+}
+
+/// Adapts a [`futures_util::io::AsyncRead`] and/or [`futures_util::io::AsyncWrite`] into an [`AsyncByteRead`]/[`AsyncByteWrite`].
+#[cfg(feature = "futures")]
+#[derive(Debug)]
+pub struct FuturesWrapper<V> {
+    inner: V,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "futures")]
+impl<V> FuturesWrapper<V> {
+    pub fn new(inner: V) -> Self {
+        Self{inner, buf: Vec::new(), pos: 0}
+    }
+
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    /// Any buffered but unconsumed read data is lost.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<V: futures_util::io::AsyncRead + Unpin> AsyncByteRead for FuturesWrapper<V> {
+    type Err = std::io::Error;
+
+    fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.pos += len;
+        assert!(self.pos <= self.buf.len());
+    }
+
+    async fn fill_more(&mut self) -> Result<usize, Self::Err> {
+        use futures_util::io::AsyncReadExt;
+
+        reserve_more(&mut self.buf, &mut self.pos);
+        let start = self.buf.len();
+        self.buf.resize(self.buf.capacity(), 0);
+        let res = self.inner.read(&mut self.buf[start..]).await;
+        self.buf.truncate(start + *res.as_ref().unwrap_or(&0));
+        res
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<V: futures_util::io::AsyncWrite + Unpin> AsyncByteWrite for FuturesWrapper<V> {
+    type Err = std::io::Error;
+
+    async fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        use futures_util::io::AsyncWriteExt;
+
+        self.inner.write_all(data).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Err> {
+        use futures_util::io::AsyncWriteExt;
+
+        self.inner.flush().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::varint::UVarInt;
+    #[cfg(any(feature = "tokio", feature = "futures"))]
+    use crate::{
+        endians::NumberEncodingBE as BE,
+        varint::SVarInt,
+        lists::LengthPrefixList,
+        string::LengthPrefixString,
+        const_list::ConstListEncoder,
+        magic_bytes::magic_bytes_type,
+    };
+
+    #[cfg(any(feature = "tokio", feature = "futures"))]
+    magic_bytes_type!{
+        struct Hello[b'h', b'i'];
+    }
+
+    #[cfg(any(feature = "tokio", feature = "futures"))]
+    type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+    #[cfg(any(feature = "tokio", feature = "futures"))]
+    async fn write_all<W: AsyncByteWrite>(io: &mut W) -> Result<(), W::Err> {
+        Hello::byte_serialize_async(&(), io).await?;
+        BE::byte_serialize_async(&0xdeadbeefu32, io).await?;
+        SVarInt::byte_serialize_async(&-300, io).await?;
+        Strings::byte_serialize_async(["abc".to_string(), "".to_string(), "xyz".repeat(5000)].as_slice(), io).await?;
+        ConstListEncoder::<UVarInt>::byte_serialize_async(&[1, 200, 30000], io).await?;
+        io.flush().await
+    }
+
+    #[cfg(any(feature = "tokio", feature = "futures"))]
+    async fn read_all<R: AsyncByteRead>(io: &mut R)
+    where
+        R::Err: std::fmt::Debug,
+    {
+        Hello::byte_deserialize_async(io).await.unwrap();
+        let n:u32 = BE::byte_deserialize_async(io).await.unwrap();
+        assert_eq!(n, 0xdeadbeef);
+        let n:i64 = SVarInt::byte_deserialize_async(io).await.unwrap();
+        assert_eq!(n, -300);
+        let strings:Vec<String> = Strings::byte_deserialize_async(io).await.unwrap();
+        assert_eq!(strings, ["abc".to_string(), "".to_string(), "xyz".repeat(5000)]);
+        let arr:[u64; 3] = ConstListEncoder::<UVarInt>::byte_deserialize_async(io).await.unwrap();
+        assert_eq!(arr, [1, 200, 30000]);
+        assert!(io.read_byte().await.is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_duplex() {
+        // A tiny duplex buffer makes the reader see the data in many small pieces
+        let (a, b) = tokio::io::duplex(7);
+        let writer = tokio::spawn(async move {
+            let mut w = TokioWrapper::new(a);
+            write_all(&mut w).await.unwrap();
+        });
+        let mut r = TokioWrapper::new(b);
+        let (_, ()) = tokio::join!(writer, read_all(&mut r));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn futures_cursor() {
+        futures_executor::block_on(async {
+            let mut w = FuturesWrapper::new(futures_util::io::Cursor::new(Vec::new()));
+            write_all(&mut w).await.unwrap();
+            let data = w.into_inner().into_inner();
+            let mut r = FuturesWrapper::new(futures_util::io::Cursor::new(data));
+            read_all(&mut r).await;
+        });
+    }
+
+    #[test]
+    fn lists_keep_decoded_items() {
+        use std::cell::Cell;
+        use crate::varint::VarIntTooBig;
+
+        thread_local! {
+            static DECODES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        /// A [`UVarInt`] that counts how many times it's decoded
+        #[derive(Debug, Default, Copy, Clone)]
+        struct Counted;
+
+        impl ByteTypeId<u64> for Counted {
+            fn byte_type_id() -> Vec<&'static str> {
+                vec!["test::Counted"]
+            }
+        }
+
+        impl ByteDeserialize<u64> for Counted {
+            type ParseErr = VarIntTooBig;
+
+            fn byte_deserialize<R: crate::io::ByteRead>(io: &mut R) -> Result<u64, ParseOrIOError<Self::ParseErr, R::Err>> {
+                DECODES.with(|d| d.set(d.get() + 1));
+                UVarInt::byte_deserialize(io)
+            }
+        }
+
+        impl BufferedAsyncDeserialize<u64> for Counted {}
+
+        /// Hands over one byte at a time
+        struct Trickle<'a> {
+            rest: &'a [u8],
+            buf: Vec<u8>,
+        }
+
+        impl AsyncByteRead for Trickle<'_> {
+            type Err = EndOfBufferError;
+
+            fn buffer(&self) -> &[u8] {
+                &self.buf
+            }
+
+            fn consume(&mut self, len: usize) {
+                self.buf.drain(..len);
+            }
+
+            async fn fill_more(&mut self) -> Result<usize, Self::Err> {
+                let Some((b, rest)) = self.rest.split_first() else { return Ok(0) };
+                self.buf.push(*b);
+                self.rest = rest;
+                Ok(1)
+            }
+        }
+
+        let items:Vec<u64> = (0..1000).map(|n| n % 100).collect();
+        let mut data = Vec::new();
+        LengthPrefixList::<UVarInt, UVarInt>::byte_serialize(&items, &mut data).unwrap();
+
+        futures_executor::block_on(async {
+            let mut r = Trickle{rest: &data, buf: Vec::new()};
+            let decoded:Vec<u64> = LengthPrefixList::<UVarInt, Counted>::byte_deserialize_async(&mut r).await.unwrap();
+            assert_eq!(decoded, items);
+        });
+        // at most one attempt that comes up short per item, rather than decoding the whole list again for every byte
+        assert!(DECODES.with(Cell::get) <= 2 * items.len());
+    }
+
+    #[test]
+    fn bad_data() {
+        use crate::varint::VarIntTooBig;
+
+        struct Slice<'a>(&'a [u8]);
+
+        impl AsyncByteRead for Slice<'_> {
+            type Err = EndOfBufferError;
+
+            fn buffer(&self) -> &[u8] {
+                self.0
+            }
+
+            fn consume(&mut self, len: usize) {
+                self.0 = &self.0[len..];
+            }
+
+            async fn fill_more(&mut self) -> Result<usize, Self::Err> {
+                Ok(0)
+            }
+        }
+
+        futures_executor::block_on(async {
+            let mut r = Slice(&[0xff; 11]);
+            let e = UVarInt::byte_deserialize_async(&mut r).await.unwrap_err();
+            assert!(matches!(e, ParseOrIOError::Parse(VarIntTooBig)));
+            let mut r = Slice(&[0x80, 0x80]);
+            let e = UVarInt::byte_deserialize_async(&mut r).await.unwrap_err();
+            assert!(matches!(e, ParseOrIOError::IO(EndOfBufferError)));
+            assert_eq!(r.buffer().len(), 2);
+        });
+    }
+}
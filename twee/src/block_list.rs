@@ -0,0 +1,490 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+use crate::io::{ByteRead, ByteWrite, Counting};
+use crate::runtime::{Decode, DecodeWith, DynBlockList, Encode};
+use crate::serde::{ByteConstTypeId, ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
+
+/// Each block starts with just its item count.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CountOnly;
+
+/// Each block starts with its item count, then its size in bytes, so readers can skip whole blocks.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CountAndSize;
+
+pub trait BlockHeader: Default + Copy {
+    const HAS_SIZE: bool;
+    const TYPE_NAME: &'static str;
+}
+
+impl BlockHeader for CountOnly {
+    const HAS_SIZE: bool = false;
+    const TYPE_NAME: &'static str = "twee::BlockList<";
+}
+
+impl BlockHeader for CountAndSize {
+    const HAS_SIZE: bool = true;
+    const TYPE_NAME: &'static str = "twee::SizedBlockList<";
+}
+
+/// A list written as a series of blocks, each a count encoded with `LE` followed by that many items encoded with `VE`, ending with a zero count.
+///
+/// Unlike [`LengthPrefixList`][`crate::lists::LengthPrefixList`] the total length never has to be known, so lists can be written from iterators with [`BlockListWriter`] and read lazily with [`BlockList::iter`].
+#[derive(Default, Debug, Clone, Copy)]
+pub struct BlockList<LE, VE, B = CountOnly> {
+    length_encoder: PhantomData<LE>,
+    value_encoder: PhantomData<VE>,
+    header: PhantomData<B>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlockListError<L, V> {
+    /// A block's count or size couldn't be parsed
+    LengthParseError(L),
+    ItemParseError{idx: u64, error: V},
+    /// A block's items didn't take up the number of bytes its header said
+    BlockSizeMismatch{expected: u64, found: u64},
+}
+
+impl<L, V> fmt::Display for BlockListError<L, V>
+where
+    L: fmt::Display,
+    V: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthParseError(e) => write!(f, "invalid block header: {}", e),
+            Self::ItemParseError{idx, error} => write!(f, "invalid list item at index {}: {}", idx, error),
+            Self::BlockSizeMismatch{expected, found} => write!(f, "block of {} bytes took up {}", expected, found),
+        }
+    }
+}
+
+impl<L, V> std::error::Error for BlockListError<L, V>
+where
+    L: std::error::Error + 'static,
+    V: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LengthParseError(e) => Some(e),
+            Self::ItemParseError{error, ..} => Some(error),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_byte_type {
+    ($v:ident, $t:ty) => {
+        impl<LE, VE, B, $v> ByteTypeId<$t> for BlockList<LE, VE, B>
+        where
+            LE: ByteTypeId<u64>,
+            VE: ByteTypeId<$v>,
+            B: BlockHeader,
+        {
+            fn byte_type_id() -> Vec<&'static str> {
+                let mut res = Vec::new();
+                res.push(B::TYPE_NAME);
+                res.extend_from_slice(LE::byte_type_id().as_slice());
+                res.push(",");
+                res.extend_from_slice(VE::byte_type_id().as_slice());
+                res.push(">");
+                res
+            }
+
+            fn type_descriptor() -> TypeDescriptor {
+                TypeDescriptor::new(TypeKind::List, B::TYPE_NAME.trim_end_matches('<'), vec![
+                    TypeParam::Type(LE::type_descriptor()),
+                    TypeParam::Type(VE::type_descriptor()),
+                ])
+            }
+        }
+
+        impl<LE, VE, B, $v> ByteConstTypeId<$t> for BlockList<LE, VE, B>
+        where
+            LE: ByteConstTypeId<u64>,
+            VE: ByteConstTypeId<$v>,
+            B: BlockHeader,
+        {
+            const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+                ConstTypeId::Str(B::TYPE_NAME),
+                LE::TYPE_ID,
+                ConstTypeId::Str(","),
+                VE::TYPE_ID,
+                ConstTypeId::Str(">"),
+            ]);
+        }
+    }
+}
+
+impl_byte_type!{V, Vec<V>}
+impl_byte_type!{V, [V]}
+
+impl<LE, VE, B> BlockList<LE, VE, B> {
+    /// Reads the list one item at a time. The reader is only left after the end of the list once the iterator has returned `None`.
+    pub fn iter<V, R: ByteRead>(io: &mut R) -> BlockListIter<'_, LE, VE, B, V, R>
+    where
+        B: BlockHeader,
+    {
+        BlockListIter{
+            io,
+            cursor: BlockCursor::new(B::HAS_SIZE),
+            length_encoder: PhantomData,
+            value_encoder: PhantomData,
+            header: PhantomData,
+            value: PhantomData,
+        }
+    }
+}
+
+/// Writes a block list from items pushed one at a time, buffering at most one block's worth of encoded items.
+///
+/// [`BlockListWriter::finish`] must be called to write the terminating zero count.
+#[derive(Debug)]
+pub struct BlockListWriter<LE, VE, B, W> {
+    io: W,
+    block: Vec<u8>,
+    count: u64,
+    block_len: u64,
+    length_encoder: PhantomData<LE>,
+    value_encoder: PhantomData<VE>,
+    header: PhantomData<B>,
+}
+
+impl<LE, VE, B, W> BlockListWriter<LE, VE, B, W>
+where
+    LE: ByteSerialize<u64>,
+    B: BlockHeader,
+    W: ByteWrite,
+{
+    /// Writes out a block every `block_len` items
+    pub fn new(io: W, block_len: u64) -> Self {
+        Self{
+            io,
+            block: Vec::new(),
+            count: 0,
+            block_len: block_len.max(1),
+            length_encoder: PhantomData,
+            value_encoder: PhantomData,
+            header: PhantomData,
+        }
+    }
+
+    pub fn push<V: ?Sized>(&mut self, item: &V) -> Result<(), W::Err>
+    where
+        VE: ByteSerialize<V>,
+    {
+        VE::byte_serialize(item, &mut self.block).unwrap();
+        self.count += 1;
+        if self.count == self.block_len {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    pub fn push_all<V, I>(&mut self, items: I) -> Result<(), W::Err>
+    where
+        VE: ByteSerialize<V>,
+        I: IntoIterator<Item = V>,
+    {
+        for item in items {
+            self.push(&item)?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> Result<(), W::Err> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        LE::byte_serialize(&self.count, &mut self.io)?;
+        if B::HAS_SIZE {
+            LE::byte_serialize(&(self.block.len() as u64), &mut self.io)?;
+        }
+        self.io.write_buf(&self.block)?;
+        self.block.clear();
+        self.count = 0;
+        Ok(())
+    }
+
+    /// Writes out the last partial block and the end of the list
+    pub fn finish(mut self) -> Result<W, W::Err> {
+        self.write_block()?;
+        LE::byte_serialize(&0, &mut self.io)?;
+        Ok(self.io)
+    }
+}
+
+/// How far a reader has got through a block list, shared by [`BlockListIter`] and [`DynBlockList`][crate::runtime::DynBlockList]
+#[derive(Debug, Default)]
+pub(crate) struct BlockCursor {
+    has_size: bool,
+    block_left: u64,
+    /// The size of the current block, and how much of it has been read so far
+    block_bytes: Option<(u64, u64)>,
+    idx: u64,
+    done: bool,
+}
+
+type BlockResult<T, L, P, I> = Result<T, ParseOrIOError<BlockListError<L, P>, I>>;
+
+impl BlockCursor {
+    pub(crate) fn new(has_size: bool) -> Self {
+        Self{has_size, ..Self::default()}
+    }
+
+    /// How many items of the current block haven't been read yet
+    pub(crate) fn block_left(&self) -> u64 {
+        self.block_left
+    }
+
+    /// Reads the next block header with `length`, returning whether there is another block
+    pub(crate) fn start_block<LE, P, R, C>(&mut self, io: &mut R, length: &LE, ctx: &mut C) -> BlockResult<bool, LE::ParseErr, P, R::Err>
+    where
+        LE: DecodeWith<u64, C>,
+        R: ByteRead,
+        C: ?Sized,
+    {
+        let count = length.decode_with(io, ctx).map_err(|e| e.map_parse(BlockListError::LengthParseError))?;
+        if count == 0 {
+            return Ok(false);
+        }
+        self.block_left = count;
+        if self.has_size {
+            let size = length.decode_with(io, ctx).map_err(|e| e.map_parse(BlockListError::LengthParseError))?;
+            self.block_bytes = Some((size, 0));
+        }
+        Ok(true)
+    }
+
+    /// Reads one item with `read`, which is handed a reader that keeps count of the block size
+    pub(crate) fn read_item<L, P, R, U, F>(&mut self, io: &mut R, read: F) -> BlockResult<U, L, P, R::Err>
+    where
+        R: ByteRead,
+        F: for<'b> FnOnce(&mut Counting<&'b mut R>) -> Result<U, ParseOrIOError<P, R::Err>>,
+    {
+        let idx = self.idx;
+        let mut io = Counting::new(io);
+        let item = read(&mut io).map_err(|e| e.map_parse(|error| BlockListError::ItemParseError{idx, error}))?;
+        self.idx += 1;
+        self.block_left -= 1;
+        if let Some((expected, read)) = self.block_bytes.as_mut() {
+            *read += io.position();
+            let (expected, read) = (*expected, *read);
+            if read > expected || (self.block_left == 0 && read != expected) {
+                return Err(ParseOrIOError::Parse(BlockListError::BlockSizeMismatch{expected, found: read}));
+            }
+        }
+        Ok(item)
+    }
+
+    /// Skips the unread part of the current block, which must have a size
+    fn skip_rest_of_block<L, P, R: ByteRead>(&mut self, io: &mut R) -> BlockResult<u64, L, P, R::Err> {
+        let (expected, read) = self.block_bytes.take().unwrap();
+        io.skip_buf(expected - read).map_err(ParseOrIOError::IO)?;
+        let skipped = self.block_left;
+        self.idx += skipped;
+        self.block_left = 0;
+        Ok(skipped)
+    }
+
+    /// Reads past the rest of the list, passing each item to `item`. Sized blocks are skipped whole unless `validate` is set.
+    pub(crate) fn skip_to_end<LE, P, R, C, F>(&mut self, io: &mut R, length: &LE, ctx: &mut C, validate: bool, mut item: F) -> BlockResult<(), LE::ParseErr, P, R::Err>
+    where
+        LE: DecodeWith<u64, C>,
+        R: ByteRead,
+        C: ?Sized,
+        F: for<'b> FnMut(&mut Counting<&'b mut R>, &mut C) -> Result<(), ParseOrIOError<P, R::Err>>,
+    {
+        while !self.done {
+            let res = if self.block_left == 0 {
+                self.start_block(io, length, ctx).map(|more| self.done = !more)
+            } else if !validate && self.block_bytes.is_some() {
+                self.skip_rest_of_block(io).map(drop)
+            } else {
+                self.read_item(io, |io| item(io, ctx))
+            };
+            if res.is_err() {
+                self.done = true;
+            }
+            res?;
+        }
+        Ok(())
+    }
+}
+
+/// Lazily reads the items of a [`BlockList`]. Iteration ends after the first error.
+#[derive(Debug)]
+pub struct BlockListIter<'r, LE, VE, B, V, R> {
+    io: &'r mut R,
+    cursor: BlockCursor,
+    length_encoder: PhantomData<LE>,
+    value_encoder: PhantomData<VE>,
+    header: PhantomData<B>,
+    value: PhantomData<fn() -> V>,
+}
+
+type BlockListResult<T, LE, VE, V, R> = Result<T, ParseOrIOError<BlockListError<<LE as ByteDeserialize<u64>>::ParseErr, <VE as ByteDeserialize<V>>::ParseErr>, <R as ByteRead>::Err>>;
+
+impl<'r, LE, VE, V, R> BlockListIter<'r, LE, VE, CountAndSize, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    R: ByteRead,
+{
+    /// Skips the rest of the current block without decoding its items, or the whole next block if the current one has been read to the end.
+    ///
+    /// Returns how many items were skipped.
+    pub fn skip_block(&mut self) -> BlockListResult<u64, LE, VE, V, R> {
+        let cursor = &mut self.cursor;
+        if cursor.done {
+            return Ok(0);
+        }
+        if cursor.block_left == 0 && !cursor.start_block(self.io, &LE::default(), &mut ())? {
+            cursor.done = true;
+            return Ok(0);
+        }
+        cursor.skip_rest_of_block(self.io)
+    }
+}
+
+impl<'r, LE, VE, B, V, R> Iterator for BlockListIter<'r, LE, VE, B, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    B: BlockHeader,
+    R: ByteRead,
+{
+    type Item = BlockListResult<V, LE, VE, V, R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = &mut self.cursor;
+        if cursor.done {
+            return None;
+        }
+        if cursor.block_left == 0 {
+            match cursor.start_block(self.io, &LE::default(), &mut ()) {
+                Ok(true) => (),
+                Ok(false) => {
+                    cursor.done = true;
+                    return None;
+                },
+                Err(e) => {
+                    cursor.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        let res = cursor.read_item(self.io, |io| VE::byte_deserialize(io));
+        cursor.done = res.is_err();
+        Some(res)
+    }
+}
+
+impl<LE, VE, B, V> ByteDeserialize<Vec<V>> for BlockList<LE, VE, B>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    B: BlockHeader,
+{
+    type ParseErr = BlockListError<LE::ParseErr, VE::ParseErr>;
+
+    fn byte_deserialize<R: ByteRead>(io: &mut R) -> Result<Vec<V>, ParseOrIOError<Self::ParseErr, R::Err>> {
+        DynBlockList::new(LE::default(), VE::default(), B::default()).decode(io)
+    }
+
+    /// Skips sized blocks whole, without looking at their items.
+    fn byte_skip<R: ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        Decode::<Vec<V>>::skip(&DynBlockList::new(LE::default(), VE::default(), B::default()), io)
+    }
+
+    fn byte_validate<R: ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        BlockCursor::new(B::HAS_SIZE).skip_to_end(io, &LE::default(), &mut (), true, |io, _| VE::byte_validate(io))
+    }
+}
+
+/// Writes everything as a single block.
+impl<LE, VE, B, V> ByteSerialize<[V]> for BlockList<LE, VE, B>
+where
+    LE: ByteSerialize<u64>,
+    VE: ByteSerialize<V>,
+    B: BlockHeader,
+{
+    fn byte_serialize<W: ByteWrite>(item: &[V], io: &mut W) -> Result<(), W::Err> {
+        DynBlockList::new(LE::default(), VE::default(), B::default()).encode(item, io)
+    }
+}
+
+impl<LE, VE, B, V> ByteSerialize<Vec<V>> for BlockList<LE, VE, B>
+where
+    LE: ByteSerialize<u64>,
+    VE: ByteSerialize<V>,
+    B: BlockHeader,
+{
+    fn byte_serialize<W: ByteWrite>(item: &Vec<V>, io: &mut W) -> Result<(), W::Err> {
+        <Self as ByteSerialize<[V]>>::byte_serialize(item, io)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::ByteCursor;
+    use crate::varint::UVarInt;
+    use crate::string::LengthPrefixString;
+
+    type Str = LengthPrefixString<UVarInt>;
+
+    #[test]
+    fn blarg() {
+        let strings:Vec<String> = (0..10).map(|n| "x".repeat(n)).collect();
+        crate::assert_serde::<BlockList<UVarInt, Str>, _>(&strings);
+        crate::assert_serde::<BlockList<UVarInt, Str, CountAndSize>, _>(&strings);
+        crate::assert_serde::<BlockList<UVarInt, Str>, _>(&Vec::<String>::new());
+
+        let mut w = BlockListWriter::<UVarInt, Str, CountOnly, _>::new(Vec::new(), 4);
+        w.push_all(strings.iter().cloned()).unwrap();
+        let mut data = w.finish().unwrap();
+        data.push(0xaa);
+        // three blocks of 4, 4 and 2
+        assert_eq!(&data[..2], &[4, 0]);
+
+        let mut cur = ByteCursor::new(&data);
+        let read:Vec<String> = BlockList::<UVarInt, Str>::iter(&mut cur).map(Result::unwrap).collect();
+        assert_eq!(read, strings);
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+    }
+
+    #[test]
+    fn skip_blocks() {
+        type SizedList = BlockList<UVarInt, Str, CountAndSize>;
+        let mut w = BlockListWriter::<UVarInt, Str, CountAndSize, _>::new(Vec::new(), 3);
+        for n in 0..8 {
+            w.push(n.to_string().as_str()).unwrap();
+        }
+        let data = w.finish().unwrap();
+
+        let mut cur = ByteCursor::new(&data);
+        let mut it = SizedList::iter::<String, _>(&mut cur);
+        assert_eq!(it.next().unwrap().unwrap(), "0");
+        assert_eq!(it.skip_block().unwrap(), 2);
+        assert_eq!(it.skip_block().unwrap(), 3);
+        assert_eq!(it.next().unwrap().unwrap(), "6");
+        assert_eq!(it.next().unwrap().unwrap(), "7");
+        assert!(it.next().is_none());
+        assert!(cur.at_end());
+
+        let mut cur = ByteCursor::new(&data);
+        <SizedList as ByteDeserialize<Vec<String>>>::byte_skip(&mut cur).unwrap();
+        assert!(cur.at_end());
+
+        // a block whose size doesn't match its items
+        let bad = [2, 3, 1, b'a', 1, b'b', 0];
+        let res:Result<Vec<String>, _> = SizedList::byte_deserialize(&mut ByteCursor::new(&bad));
+        assert_eq!(res, Err(ParseOrIOError::Parse(BlockListError::BlockSizeMismatch{expected: 3, found: 4})));
+        let res = <SizedList as ByteDeserialize<Vec<String>>>::byte_validate(&mut ByteCursor::new(&bad));
+        assert_eq!(res, Err(ParseOrIOError::Parse(BlockListError::BlockSizeMismatch{expected: 3, found: 4})));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+use crate::io::{ByteRead, ByteWrite, SeekableByteWrite, Take, TakeError};
+use crate::runtime::{Decode, DynByteLengthPrefixed, Encode};
+use crate::serde::{ByteConstSerialize, ByteConstTypeId, ByteDeserialize, ByteSerialize, ByteTypeId, ParseOrIOError};
+
+/// A placeholder for a byte length encoded with the fixed-width `LE`, to be filled in once whatever follows it has been written.
+#[derive(Debug, Copy, Clone)]
+#[must_use = "the slot holds zeros until it is filled"]
+pub struct LengthSlot<LE> {
+    pos: u64,
+    length_encoder: PhantomData<LE>,
+}
+
+impl<LE> LengthSlot<LE>
+where
+    LE: ByteConstSerialize<u64>,
+    [(); LE::BYTE_SIZE]: ,
+{
+    pub fn reserve<W: SeekableByteWrite>(io: &mut W) -> Result<Self, W::Err> {
+        let pos = io.position()?;
+        io.write_buf(&[0; LE::BYTE_SIZE])?;
+        Ok(Self{
+            pos,
+            length_encoder: PhantomData,
+        })
+    }
+
+    /// Patches in how many bytes have been written since the slot, and returns that length
+    pub fn fill<W: SeekableByteWrite>(self, io: &mut W) -> Result<u64, W::Err> {
+        let len = io.position()? - self.pos - LE::BYTE_SIZE as u64;
+        let mut buf = [0; LE::BYTE_SIZE];
+        LE::byte_const_serialize(&len, &mut buf);
+        io.patch(self.pos, &buf)?;
+        Ok(len)
+    }
+}
+
+/// Encodes a value with `E`, preceded by its size in bytes encoded with `LE`, so readers can skip or bound it without understanding `E`.
+///
+/// Works for lists and strings. `LE` must be fixed-width for serializing. Through [`ByteSerialize::byte_serialize_seekable`] the size is back-patched rather than worked out beforehand, which saves a pass over the value for every level of nesting.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ByteLengthPrefixed<LE, E> {
+    length_encoder: PhantomData<LE>,
+    encoder: PhantomData<E>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ByteLengthError<L, V> {
+    LengthParseError(L),
+    ValueParseError(V),
+    /// The value claims to run past the end of its `len` bytes
+    Overrun{len: u64},
+    /// The value was parsed without using all of its bytes
+    TrailingBytes{unused: u64},
+}
+
+impl<L, V> fmt::Display for ByteLengthError<L, V>
+where
+    L: fmt::Display,
+    V: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthParseError(e) => write!(f, "invalid length: {}", e),
+            Self::ValueParseError(e) => e.fmt(f),
+            Self::Overrun{len} => write!(f, "value runs past the end of its {} bytes", len),
+            Self::TrailingBytes{unused} => write!(f, "{} unused bytes after value", unused),
+        }
+    }
+}
+
+impl<L, V> std::error::Error for ByteLengthError<L, V>
+where
+    L: std::error::Error + 'static,
+    V: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LengthParseError(e) => Some(e),
+            Self::ValueParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// These can't be blanket impls over every `T` without overlapping the ones for `ByteConst*` encoders, so they're spelled out per value type like the other combinators
+macro_rules! impl_byte_length_prefixed {
+    ($([$($g:ident),*] $t:ty;)*) => {
+        $(
+            impl<LE, E, $($g),*> ByteTypeId<$t> for ByteLengthPrefixed<LE, E>
+            where
+                LE: ByteTypeId<u64>,
+                E: ByteTypeId<$t>,
+            {
+                fn byte_type_id() -> Vec<&'static str> {
+                    let mut res = Vec::new();
+                    res.push("twee::ByteLengthPrefixed<");
+                    res.extend_from_slice(LE::byte_type_id().as_slice());
+                    res.push(",");
+                    res.extend_from_slice(E::byte_type_id().as_slice());
+                    res.push(">");
+                    res
+                }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    TypeDescriptor::new(TypeKind::Wrapper, "twee::ByteLengthPrefixed", vec![
+                        TypeParam::Type(LE::type_descriptor()),
+                        TypeParam::Type(E::type_descriptor()),
+                    ])
+                }
+            }
+
+            impl<LE, E, $($g),*> ByteConstTypeId<$t> for ByteLengthPrefixed<LE, E>
+            where
+                LE: ByteConstTypeId<u64>,
+                E: ByteConstTypeId<$t>,
+            {
+                const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+                    ConstTypeId::Str("twee::ByteLengthPrefixed<"),
+                    LE::TYPE_ID,
+                    ConstTypeId::Str(","),
+                    E::TYPE_ID,
+                    ConstTypeId::Str(">"),
+                ]);
+            }
+
+            impl<LE, E, $($g),*> ByteSerialize<$t> for ByteLengthPrefixed<LE, E>
+            where
+                LE: ByteConstSerialize<u64>,
+                [(); LE::BYTE_SIZE]: ,
+                E: ByteSerialize<$t>,
+            {
+                fn byte_serialize<W: ByteWrite>(item: &$t, io: &mut W) -> Result<(), W::Err> {
+                    DynByteLengthPrefixed::new(LE::default(), E::default()).encode(item, io)
+                }
+
+                fn byte_serialize_seekable<W: SeekableByteWrite>(item: &$t, io: &mut W) -> Result<(), W::Err> {
+                    let slot = LengthSlot::<LE>::reserve(io)?;
+                    E::byte_serialize_seekable(item, io)?;
+                    slot.fill(io)?;
+                    Ok(())
+                }
+
+                fn size(item: &$t) -> u64 {
+                    LE::BYTE_SIZE as u64 + E::size(item)
+                }
+            }
+        )*
+    };
+}
+
+impl_byte_length_prefixed!{
+    [V] Vec<V>;
+    [V] [V];
+    [] String;
+    [] str;
+}
+
+type ByteLengthResult<T, L, V, I> = Result<T, ParseOrIOError<ByteLengthError<L, V>, I>>;
+
+/// Runs `inner` on a reader bounded to the next `len` bytes and checks that it used all of them
+pub(crate) fn read_bounded<'a, L, P, R, U, F>(io: &'a mut R, len: u64, inner: F) -> ByteLengthResult<U, L, P, R::Err>
+where
+    R: ByteRead,
+    F: FnOnce(&mut Take<&'a mut R>) -> Result<U, ParseOrIOError<P, TakeError<R::Err>>>,
+{
+    let mut bounded = Take::new(io, len);
+    let value = inner(&mut bounded).map_err(|e| match e {
+        ParseOrIOError::Parse(e) => ParseOrIOError::Parse(ByteLengthError::ValueParseError(e)),
+        ParseOrIOError::IO(TakeError::LimitExceeded{..}) => ParseOrIOError::Parse(ByteLengthError::Overrun{len}),
+        ParseOrIOError::IO(TakeError::Inner(e)) => ParseOrIOError::IO(e),
+    })?;
+    if bounded.limit() != 0 {
+        return Err(ParseOrIOError::Parse(ByteLengthError::TrailingBytes{unused: bounded.limit()}));
+    }
+    Ok(value)
+}
+
+macro_rules! impl_byte_length_deserialize {
+    ($([$($g:ident),*] $t:ty;)*) => {
+        $(
+            impl<LE, E, $($g),*> ByteDeserialize<$t> for ByteLengthPrefixed<LE, E>
+            where
+                LE: ByteDeserialize<u64>,
+                E: ByteDeserialize<$t>,
+            {
+                type ParseErr = ByteLengthError<LE::ParseErr, E::ParseErr>;
+
+                fn byte_deserialize<R: ByteRead>(io: &mut R) -> Result<$t, ParseOrIOError<Self::ParseErr, R::Err>> {
+                    DynByteLengthPrefixed::new(LE::default(), E::default()).decode(io)
+                }
+
+                /// Skips the whole value using its length, without looking inside it.
+                fn byte_skip<R: ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+                    Decode::<$t>::skip(&DynByteLengthPrefixed::new(LE::default(), E::default()), io)
+                }
+
+                fn byte_validate<R: ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+                    let len = LE::byte_deserialize(io).map_err(|e| e.map_parse(ByteLengthError::LengthParseError))?;
+                    read_bounded(io, len, E::byte_validate)
+                }
+            }
+        )*
+    };
+}
+
+impl_byte_length_deserialize!{
+    [V] Vec<V>;
+    [] String;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use crate::endians::NumberEncodingLE as LE;
+    use crate::io::{ByteCursor, SliceWriter};
+    use crate::io_wrap::BufWrapper;
+    use crate::lists::LengthPrefixList;
+    use crate::string::LengthPrefixString;
+    use crate::varint::UVarInt;
+
+    type Msg = ByteLengthPrefixed<LE, LengthPrefixList<UVarInt, ByteLengthPrefixed<LE, LengthPrefixString<UVarInt>>>>;
+
+    #[test]
+    fn blarg() {
+        let strings = vec!["hello".to_string(), String::new(), "x".repeat(300)];
+        crate::assert_serde::<Msg, _>(&strings);
+
+        let mut two_pass = Vec::new();
+        Msg::byte_serialize(&strings, &mut two_pass).unwrap();
+
+        let mut one_pass = Vec::new();
+        Msg::byte_serialize_seekable(&strings, &mut one_pass).unwrap();
+        assert_eq!(one_pass, two_pass);
+
+        let mut arr = [0; 512];
+        let mut w = SliceWriter::new(&mut arr);
+        Msg::byte_serialize_seekable(&strings, &mut w).unwrap();
+        assert_eq!(w.written(), two_pass);
+        assert_eq!(w.patch(w.position() as u64 - 1, &[0, 0]), Err(crate::io::OutOfSpaceError));
+
+        // a small buffer makes the outer length get patched after it has already been flushed
+        let mut w = BufWrapper::with_capacity(16, Cursor::new(Vec::new()));
+        Msg::byte_serialize_seekable(&strings, &mut w).unwrap();
+        assert_eq!(w.into_inner().unwrap().into_inner(), two_pass);
+    }
+
+    #[test]
+    fn bad_lengths() {
+        type Str = ByteLengthPrefixed<LE, LengthPrefixString<UVarInt>>;
+        let mut data = Vec::new();
+        Str::byte_serialize_seekable("abc", &mut data).unwrap();
+        assert_eq!(data, [4, 0, 0, 0, 0, 0, 0, 0, 3, b'a', b'b', b'c']);
+
+        let mut long = data.clone();
+        long[0] = 5;
+        long.push(0);
+        let res:Result<String, _> = Str::byte_deserialize(&mut ByteCursor::new(&long));
+        assert_eq!(res, Err(ParseOrIOError::Parse(ByteLengthError::TrailingBytes{unused: 1})));
+
+        let mut short = data.clone();
+        short[0] = 3;
+        let res:Result<String, _> = Str::byte_deserialize(&mut ByteCursor::new(&short));
+        assert_eq!(res, Err(ParseOrIOError::Parse(ByteLengthError::Overrun{len: 3})));
+    }
+}
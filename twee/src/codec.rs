@@ -0,0 +1,315 @@
+use std::error::Error as StdError;
+use std::io as stdio;
+use std::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::error::{Error, ErrorKind};
+use crate::frame::DEFAULT_MAX_FRAME_SIZE;
+use crate::incremental::{decode_prefix, Decoded};
+use crate::io::{ByteCursor, ByteWrite};
+use crate::serde::{ByteDeserialize, ByteSerialize, ParseOrIOError};
+
+impl ByteWrite for BytesMut {
+    type Err = !;
+
+    fn write_byte(&mut self, data: u8) -> Result<(), !> {
+        self.put_u8(data);
+        Ok(())
+    }
+
+    fn write_buf(&mut self, data: &[u8]) -> Result<(), !> {
+        self.put_slice(data);
+        Ok(())
+    }
+}
+
+/// Messages follow each other directly, and the decoder works out where each one ends.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Unframed;
+
+/// Each message is preceded by its length in bytes, encoded with `LE`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct LengthPrefixed<LE> {
+    length_encoder: PhantomData<LE>,
+}
+
+/// A [`tokio_util::codec`] [`Encoder`]/[`Decoder`] that encodes and decodes messages with `E`.
+///
+/// Decoding produces `T`s, and anything that `E` can serialize can be encoded by reference.
+#[derive(Debug)]
+pub struct TweeCodec<E, T, F = Unframed> {
+    max_frame_size: u64,
+    encoder: PhantomData<E>,
+    value: PhantomData<fn() -> T>,
+    framing: PhantomData<F>,
+}
+
+impl<E, T, F> Clone for TweeCodec<E, T, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E, T, F> Copy for TweeCodec<E, T, F> {}
+
+impl<E, T> Default for TweeCodec<E, T, Unframed> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, T> TweeCodec<E, T, Unframed> {
+    pub fn new() -> Self {
+        Self{
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            encoder: PhantomData,
+            value: PhantomData,
+            framing: PhantomData,
+        }
+    }
+}
+
+impl<E, T, LE> TweeCodec<E, T, LengthPrefixed<LE>> {
+    pub fn length_prefixed() -> Self {
+        Self{
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            encoder: PhantomData,
+            value: PhantomData,
+            framing: PhantomData,
+        }
+    }
+}
+
+impl<E, T, F> TweeCodec<E, T, F> {
+    /// Refuses to encode or decode any message longer than `max` bytes, not counting the length prefix. Defaults to [`DEFAULT_MAX_FRAME_SIZE`].
+    ///
+    /// Without a length prefix, a message's length isn't known until all of it has arrived, so the decoder gives up once more than `max` bytes are buffered without a complete message.
+    pub fn with_max_frame_size(mut self, max: u64) -> Self {
+        self.max_frame_size = max;
+        self
+    }
+
+    pub fn max_frame_size(&self) -> u64 {
+        self.max_frame_size
+    }
+
+    fn check_frame_size(&self, len: u64) -> Result<(), stdio::Error> {
+        if len > self.max_frame_size {
+            return Err(stdio::Error::new(
+                stdio::ErrorKind::InvalidData,
+                format!("frame of {} bytes exceeds maximum of {}", len, self.max_frame_size),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn length_overflow() -> stdio::Error {
+    stdio::Error::new(stdio::ErrorKind::InvalidData, "frame length overflows")
+}
+
+fn parse_error<P: StdError + Send + Sync + 'static>(e: P) -> stdio::Error {
+    Error::new(ErrorKind::Parse, e).into()
+}
+
+impl<E, T> Decoder for TweeCodec<E, T, Unframed>
+where
+    E: ByteDeserialize<T>,
+    E::ParseErr: StdError + Send + Sync + 'static,
+{
+    type Item = T;
+    type Error = stdio::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, stdio::Error> {
+        match decode_prefix::<E, T>(src).map_err(parse_error)? {
+            Decoded::Complete{value, consumed} => {
+                src.advance(consumed);
+                Ok(Some(value))
+            },
+            Decoded::Incomplete{needed} => {
+                let total = src.len().checked_add(needed).ok_or_else(length_overflow)?;
+                self.check_frame_size(total as u64)?;
+                src.reserve(needed);
+                Ok(None)
+            },
+        }
+    }
+}
+
+impl<E, T, LE> Decoder for TweeCodec<E, T, LengthPrefixed<LE>>
+where
+    E: ByteDeserialize<T>,
+    E::ParseErr: StdError + Send + Sync + 'static,
+    LE: ByteDeserialize<u64>,
+    LE::ParseErr: StdError + Send + Sync + 'static,
+{
+    type Item = T;
+    type Error = stdio::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, stdio::Error> {
+        let (len, prefix_len) = match decode_prefix::<LE, u64>(src).map_err(parse_error)? {
+            Decoded::Complete{value, consumed} => (value, consumed),
+            Decoded::Incomplete{..} => return Ok(None),
+        };
+        self.check_frame_size(len)?;
+        let len:usize = len.try_into().map_err(|_| length_overflow())?;
+        let total = prefix_len.checked_add(len).ok_or_else(length_overflow)?;
+        if src.len() < total {
+            src.reserve(total - src.len());
+            return Ok(None);
+        }
+        src.advance(prefix_len);
+        let frame = src.split_to(len);
+        let mut cur = ByteCursor::new(&frame);
+        let value = E::byte_deserialize(&mut cur).map_err(|e| match e {
+            ParseOrIOError::Parse(e) => parse_error(e),
+            ParseOrIOError::IO(e) => stdio::Error::new(stdio::ErrorKind::InvalidData, Error::new(ErrorKind::UnexpectedEof, e)),
+        })?;
+        if !cur.at_end() {
+            return Err(stdio::Error::new(
+                stdio::ErrorKind::InvalidData,
+                format!("{} trailing bytes after message in frame", cur.remaining()),
+            ));
+        }
+        Ok(Some(value))
+    }
+}
+
+impl<'a, E, T, I> Encoder<&'a I> for TweeCodec<E, T, Unframed>
+where
+    E: ByteSerialize<I>,
+    I: ?Sized,
+{
+    type Error = stdio::Error;
+
+    fn encode(&mut self, item: &'a I, dst: &mut BytesMut) -> Result<(), stdio::Error> {
+        let size = E::size(item);
+        self.check_frame_size(size)?;
+        dst.reserve(size.try_into().unwrap());
+        E::byte_serialize(item, dst).unwrap();
+        Ok(())
+    }
+}
+
+impl<'a, E, T, LE, I> Encoder<&'a I> for TweeCodec<E, T, LengthPrefixed<LE>>
+where
+    E: ByteSerialize<I>,
+    LE: ByteSerialize<u64>,
+    I: ?Sized,
+{
+    type Error = stdio::Error;
+
+    fn encode(&mut self, item: &'a I, dst: &mut BytesMut) -> Result<(), stdio::Error> {
+        let size = E::size(item);
+        self.check_frame_size(size)?;
+        dst.reserve((LE::size(&size) + size).try_into().unwrap());
+        LE::byte_serialize(&size, dst).unwrap();
+        E::byte_serialize(item, dst).unwrap();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_util::codec::{FramedRead, FramedWrite};
+    use crate::endians::NumberEncodingBE as BE;
+    use crate::varint::UVarInt;
+    use crate::lists::LengthPrefixList;
+    use crate::string::LengthPrefixString;
+
+    type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+    fn messages() -> Vec<Vec<String>> {
+        vec![
+            vec!["hello".to_string(), "world".to_string()],
+            vec![],
+            vec!["x".repeat(3000), "y".to_string()],
+        ]
+    }
+
+    async fn round_trip<C>(codec: C)
+    where
+        C: Decoder<Item = Vec<String>, Error = stdio::Error> + for<'a> Encoder<&'a [String], Error = stdio::Error> + Copy + Send + 'static,
+    {
+        let (a, b) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            let mut w = FramedWrite::new(a, codec);
+            for m in messages() {
+                w.send(m.as_slice()).await.unwrap();
+            }
+        });
+        let decoded:Vec<Vec<String>> = FramedRead::new(b, codec).map(Result::unwrap).collect().await;
+        writer.await.unwrap();
+        assert_eq!(decoded, messages());
+    }
+
+    #[tokio::test]
+    async fn blarg() {
+        round_trip(TweeCodec::<Strings, Vec<String>>::new()).await;
+        round_trip(TweeCodec::<Strings, Vec<String>, LengthPrefixed<UVarInt>>::length_prefixed()).await;
+        round_trip(TweeCodec::<Strings, Vec<String>, LengthPrefixed<BE>>::length_prefixed().with_max_frame_size(4000)).await;
+    }
+
+    #[test]
+    fn frame_limits() {
+        let mut codec = TweeCodec::<Strings, Vec<String>, LengthPrefixed<UVarInt>>::length_prefixed().with_max_frame_size(10);
+        let mut buf = BytesMut::new();
+        let big = vec!["x".repeat(20)];
+        assert!(codec.encode(big.as_slice(), &mut buf).is_err());
+
+        // a huge length is refused before any of the frame has arrived
+        buf.extend_from_slice(&[0xff, 0x7f]);
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+
+        let mut codec = TweeCodec::<Strings, Vec<String>, LengthPrefixed<UVarInt>>::length_prefixed();
+        let mut buf = BytesMut::from(&[3, 1, 1, b'a'][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(vec!["a".to_string()]));
+        let mut buf = BytesMut::from(&[3, 1, 0, 0][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+        let mut buf = BytesMut::from(&[3, 1, 5][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        // the frame is complete, but the string inside claims to run past its end
+        buf.extend_from_slice(b"a");
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+
+        let mut codec = TweeCodec::<Strings, Vec<String>>::new().with_max_frame_size(10);
+        let mut buf = BytesMut::from(&[1, 20, b'a'][..]);
+        assert!(codec.decode(&mut buf).is_err());
+
+        // a small frame claiming a huge number of items doesn't allocate room for them all
+        let mut codec = TweeCodec::<Strings, Vec<String>, LengthPrefixed<UVarInt>>::length_prefixed().with_max_frame_size(10);
+        let mut buf = BytesMut::new();
+        let mut payload = Vec::new();
+        UVarInt::byte_serialize(&(1 << 62), &mut payload).unwrap();
+        UVarInt::byte_serialize(&(payload.len() as u64), &mut buf).unwrap();
+        buf.extend_from_slice(&payload);
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn huge_lengths() {
+        let mut max = BytesMut::new();
+        UVarInt::byte_serialize(&u64::MAX, &mut max).unwrap();
+
+        // refused by the default limit, before reserving any room for it
+        let mut codec = TweeCodec::<Strings, Vec<String>, LengthPrefixed<UVarInt>>::length_prefixed();
+        assert_eq!(codec.max_frame_size(), DEFAULT_MAX_FRAME_SIZE);
+        let mut buf = max.clone();
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+        assert!(buf.capacity() < 1024);
+
+        // with no real limit, adding the prefix length overflows
+        let mut codec = codec.with_max_frame_size(u64::MAX);
+        let mut buf = max.clone();
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+
+        let mut codec = TweeCodec::<LengthPrefixString<UVarInt>, String>::new();
+        let mut buf = max.clone();
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), stdio::ErrorKind::InvalidData);
+        assert!(buf.capacity() < 1024);
+    }
+}
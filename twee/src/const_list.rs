@@ -2,7 +2,9 @@ use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 
 use crate::io;
-use crate::serde::{ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
+use crate::runtime::{Decode, DynConstListEncoder, Encode};
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+use crate::serde::{ByteConstTypeId, ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct ConstListEncoder<VE>
@@ -16,6 +18,42 @@ pub struct ParseConstListError<E> {
     pub error: E,
 }
 
+impl<E: std::fmt::Display> std::fmt::Display for ParseConstListError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid list item at index {}: {}", self.index, self.error)
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ParseConstListError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Builds an array from `f`, stopping at the first error
+pub(crate) fn try_array_from_fn<V, E, const N: usize>(mut f: impl FnMut(usize) -> Result<V, E>) -> Result<[V; N], E> {
+    let mut data:[MaybeUninit<V>; N] = std::array::from_fn(|_| MaybeUninit::uninit());
+
+    let mut index = 0;
+    while index < data.len() {
+        match f(index) {
+            Ok(v) => {
+                data[index].write(v);
+            },
+            Err(e) => {
+                // To avoid a memory leak, we must manually drop any elements we already "filled in"
+                for i in (0..index).into_iter().rev() {
+                    unsafe { data[i].assume_init_drop() }
+                }
+                return Err(e)
+            },
+        }
+        index += 1;
+    }
+
+    Ok(data.map(|el| unsafe { MaybeUninit::assume_init(el) }))
+}
+
 impl<VE, V, const N: usize> ByteTypeId<[V; N]> for ConstListEncoder<VE>
 where
     VE: ByteTypeId<V>,
@@ -30,6 +68,29 @@ where
         res.push(">");
         res
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        let inner = VE::type_descriptor();
+        let fixed_size = inner.fixed_size.and_then(|size| size.checked_mul(N as u64));
+        TypeDescriptor::new(TypeKind::List, "twee::ConstSizeList", vec![
+            TypeParam::Type(inner),
+            TypeParam::Value(const_stringify_ints::const_str_usize::<N>().into()),
+        ]).with_fixed_size(fixed_size)
+    }
+}
+
+impl<VE, V, const N: usize> ByteConstTypeId<[V; N]> for ConstListEncoder<VE>
+where
+    VE: ByteConstTypeId<V>,
+    [V; N]: ,
+{
+    const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+        ConstTypeId::Str("twee::ConstSizeList<"),
+        VE::TYPE_ID,
+        ConstTypeId::Str(","),
+        ConstTypeId::Str(const_stringify_ints::const_str_usize::<N>()),
+        ConstTypeId::Str(">"),
+    ]);
 }
 
 impl<VE, V, const N: usize> ByteDeserialize<[V; N]> for ConstListEncoder<VE>
@@ -40,31 +101,34 @@ where
     type ParseErr = ParseConstListError<VE::ParseErr>;
 
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<[V; N], ParseOrIOError<Self::ParseErr, R::Err>> {
-        let mut data:[MaybeUninit<V>; N] = std::array::from_fn(|_| MaybeUninit::uninit());
-
-        let mut index = 0;
-        while index < data.len() {
-            let res = VE::byte_deserialize(io).map_err(|error| error.map_parse(|e| ParseConstListError{index, error: e}));
-            match res {
-                Ok(v) => {
-                    data[index].write(v);
-                },
-                Err(e) => {
-                    // To avoid a memory leak, we must manually drop any elements we already "filled in"
-                    for i in (0..index).into_iter().rev() {
-                        unsafe { data[i].assume_init_drop() }
-                    }
-                    return Err(e)
-                },
-            }
-            index += 1;
+        DynConstListEncoder::new(VE::default()).decode(io)
+    }
+
+    /// Decodes each item in place. On failure, the items before the failing one have been decoded, the failing one is in whatever state its own decoder leaves it, and the rest are untouched.
+    fn byte_deserialize_in_place<R: io::ByteRead>(io: &mut R, place: &mut [V; N]) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        for (index, el) in place.iter_mut().enumerate() {
+            VE::byte_deserialize_in_place(io, el).map_err(|error| error.map_parse(|e| ParseConstListError{index, error: e}))?;
         }
+        Ok(())
+    }
+
+    /// Items of a constant size are skipped all at once.
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        match <Self as ByteDeserialize<[V; N]>>::guess_size() {
+            Some(size) => io.skip_buf(size as u64).map_err(ParseOrIOError::IO),
+            None => Decode::<[V; N]>::skip(&DynConstListEncoder::new(VE::default()), io),
+        }
+    }
 
-        Ok(data.map(|el| unsafe { MaybeUninit::assume_init(el) }))   
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        for index in 0..N {
+            VE::byte_validate(io).map_err(|error| error.map_parse(|e| ParseConstListError{index, error: e}))?;
+        }
+        Ok(())
     }
 
     fn guess_size() -> Option<usize> {
-        VE::guess_size().map(|el_size| el_size * N)
+        VE::guess_size().and_then(|el_size| el_size.checked_mul(N))
     }
 }
 
@@ -74,14 +138,18 @@ where
     [V; N]: ,
 {
     fn byte_serialize<W: io::ByteWrite>(item: &[V; N], io: &mut W) -> Result<(), W::Err> {
-        for i in 0..N {
-            VE::byte_serialize(&item[i], io)?;
+        DynConstListEncoder::new(VE::default()).encode(item, io)
+    }
+
+    fn byte_serialize_seekable<W: io::SeekableByteWrite>(item: &[V; N], io: &mut W) -> Result<(), W::Err> {
+        for el in item.iter() {
+            VE::byte_serialize_seekable(el, io)?;
         }
         Ok(())
     }
 
     fn size(item: &[V; N]) -> u64 {
-        item.iter().map(VE::size).sum()
+        DynConstListEncoder::new(VE::default()).encoded_size(item)
     }
 }
 
@@ -95,4 +163,36 @@ mod test {
         let arr:[u64; 4] = [0,1,12345,99];
         crate::assert_serde::<ConstListEncoder<BE>,_>(&arr);
     }
+
+    #[test]
+    fn skip_const_size() {
+        use crate::magic_bytes::magic_bytes_type;
+
+        magic_bytes_type!{
+            struct Tag[b'x'];
+        }
+
+        // the items are skipped over by size, so they aren't checked
+        let data = [b'a', b'b', b'c', 0xaa];
+        let mut cur = io::ByteCursor::new(&data);
+        <ConstListEncoder<Tag> as ByteDeserialize<[(); 3]>>::byte_skip(&mut cur).unwrap();
+        assert_eq!(cur.position(), 3);
+        assert!(<ConstListEncoder<Tag> as ByteDeserialize<[(); 3]>>::byte_deserialize(&mut io::ByteCursor::new(&data)).is_err());
+        assert!(<ConstListEncoder<Tag> as ByteDeserialize<[(); 5]>>::byte_skip(&mut io::ByteCursor::new(&data)).is_err());
+    }
+
+    #[test]
+    fn in_place() {
+        use crate::endians::NumberEncodingBE as BE;
+        let mut data = Vec::new();
+        ConstListEncoder::<BE>::byte_serialize(&[1u16, 2, 3], &mut data).unwrap();
+        let mut place = [9u16; 3];
+        ConstListEncoder::<BE>::byte_deserialize_in_place(&mut io::ByteCursor::new(&data), &mut place).unwrap();
+        assert_eq!(place, [1, 2, 3]);
+
+        let mut place = [9u16; 3];
+        let res = ConstListEncoder::<BE>::byte_deserialize_in_place(&mut io::ByteCursor::new(&data[..3]), &mut place);
+        assert!(res.is_err());
+        assert_eq!(place, [1, 9, 9]);
+    }
 }
\ No newline at end of file
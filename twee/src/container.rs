@@ -0,0 +1,385 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io as stdio;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::endians::NumberEncodingLE;
+use crate::error::{Error, ErrorKind};
+use crate::io::{ByteCursor, EndOfBufferError, Take, TakeError};
+use crate::io_wrap::BufWrapper;
+use crate::magic_bytes::{magic_bytes_type, MagicMismatch};
+use crate::serde::{ByteConstSize, ByteDeserialize, ByteSerialize, ByteTypeId, ParseOrIOError};
+use crate::string::LengthPrefixString;
+use crate::varint::UVarInt;
+
+magic_bytes_type!{
+    /// The first and last bytes of every container file
+    pub struct ContainerMagic[b'T', b'W', b'C', 0x01];
+}
+
+const MAGIC_LEN:usize = <ContainerMagic as ByteConstSize<()>>::BYTE_SIZE;
+/// The offset of the table of contents as a `u64` little-endian, then the magic again
+const FOOTER_LEN:usize = 8 + MAGIC_LEN;
+
+/// The version of the table of contents layout written by [`ContainerWriter`]
+pub const CONTAINER_VERSION:u64 = 1;
+
+type Str = LengthPrefixString<UVarInt>;
+
+/// A table of contents entry
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SectionInfo {
+    pub name: String,
+    /// The joined [`ByteTypeId::byte_type_id`] of the encoder the section was written with
+    pub type_id: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+fn type_id_of<E: ByteTypeId<T>, T: ?Sized>() -> String {
+    E::byte_type_id().concat()
+}
+
+#[derive(Debug)]
+pub enum ContainerError {
+    /// The file doesn't start or end with [`ContainerMagic`]
+    NotAContainer(MagicMismatch<MAGIC_LEN>),
+    UnsupportedVersion(u64),
+    /// The table of contents couldn't be parsed, or lists a section outside of the file
+    Toc(Error),
+    NoSuchSection(String),
+    /// The section was written with a different encoder than the one it's being read with
+    TypeMismatch{section: String, expected: String, found: String},
+    /// The section's contents couldn't be decoded
+    Payload(Error),
+    /// The section was decoded, but not all of it was used
+    TrailingBytes{section: String, unused: u64},
+    IO(stdio::Error),
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAContainer(e) => write!(f, "not a container file: {}", e),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported container version {}", v),
+            Self::Toc(e) => write!(f, "invalid table of contents: {}", e),
+            Self::NoSuchSection(name) => write!(f, "no section named {:?}", name),
+            Self::TypeMismatch{section, expected, found} => write!(f, "section {:?} has type {}, expecting {}", section, found, expected),
+            Self::Payload(e) => write!(f, "invalid section: {}", e),
+            Self::TrailingBytes{section, unused} => write!(f, "{} unused bytes at end of section {:?}", unused, section),
+            Self::IO(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl StdError for ContainerError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::NotAContainer(e) => Some(e),
+            Self::Toc(e) => Some(e),
+            Self::Payload(e) => Some(e),
+            Self::IO(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<stdio::Error> for ContainerError {
+    fn from(e: stdio::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+/// Writes a container: the magic, then each section one after another, then the table of contents and a footer pointing at it.
+#[derive(Debug)]
+pub struct ContainerWriter<W: Write> {
+    inner: BufWrapper<W>,
+    position: u64,
+    sections: Vec<SectionInfo>,
+}
+
+impl<W: Write> ContainerWriter<W> {
+    pub fn create(inner: W) -> stdio::Result<Self> {
+        let mut inner = BufWrapper::new(inner);
+        ContainerMagic::byte_serialize(&(), &mut inner)?;
+        Ok(Self{
+            inner,
+            position: MAGIC_LEN as u64,
+            sections: Vec::new(),
+        })
+    }
+
+    /// Encodes `item` with `E` as a new section called `name`
+    pub fn add_section<E, T>(&mut self, name: &str, item: &T) -> stdio::Result<()>
+    where
+        E: ByteSerialize<T>,
+        T: ?Sized,
+    {
+        if self.sections.iter().any(|s| s.name == name) {
+            return Err(stdio::Error::new(stdio::ErrorKind::InvalidInput, format!("duplicate section {:?}", name)));
+        }
+        let len = E::size(item);
+        E::byte_serialize(item, &mut self.inner)?;
+        self.sections.push(SectionInfo{
+            name: name.to_string(),
+            type_id: type_id_of::<E, T>(),
+            offset: self.position,
+            len,
+        });
+        self.position += len;
+        Ok(())
+    }
+
+    pub fn sections(&self) -> &[SectionInfo] {
+        &self.sections
+    }
+
+    /// Writes the table of contents and footer, and flushes the inner writer
+    pub fn finish(mut self) -> stdio::Result<W> {
+        let toc_offset = self.position;
+        let w = &mut self.inner;
+        UVarInt::byte_serialize(&CONTAINER_VERSION, w)?;
+        UVarInt::byte_serialize(&(self.sections.len() as u64), w)?;
+        for s in self.sections.iter() {
+            Str::byte_serialize(&s.name, w)?;
+            Str::byte_serialize(&s.type_id, w)?;
+            UVarInt::byte_serialize(&s.offset, w)?;
+            UVarInt::byte_serialize(&s.len, w)?;
+        }
+        NumberEncodingLE::byte_serialize(&toc_offset, w)?;
+        ContainerMagic::byte_serialize(&(), w)?;
+        let mut inner = self.inner.into_inner()?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+fn parse_toc(buf: &[u8], toc_offset: u64) -> Result<Vec<SectionInfo>, ContainerError> {
+    fn toc_err<P>(e: ParseOrIOError<P, EndOfBufferError>) -> ContainerError
+    where
+        P: StdError + Send + Sync + 'static,
+    {
+        ContainerError::Toc(e.into())
+    }
+
+    let mut cur = ByteCursor::new(buf);
+    let version = UVarInt::byte_deserialize(&mut cur).map_err(toc_err)?;
+    if version != CONTAINER_VERSION {
+        return Err(ContainerError::UnsupportedVersion(version));
+    }
+    let count = UVarInt::byte_deserialize(&mut cur).map_err(toc_err)?;
+    let mut sections = Vec::new();
+    for _ in 0..count {
+        let s = SectionInfo{
+            name: Str::byte_deserialize(&mut cur).map_err(toc_err)?,
+            type_id: Str::byte_deserialize(&mut cur).map_err(toc_err)?,
+            offset: UVarInt::byte_deserialize(&mut cur).map_err(toc_err)?,
+            len: UVarInt::byte_deserialize(&mut cur).map_err(toc_err)?,
+        };
+        let in_bounds = s.offset >= MAGIC_LEN as u64 && s.offset.checked_add(s.len).is_some_and(|end| end <= toc_offset);
+        if !in_bounds {
+            return Err(ContainerError::Toc(Error::new(
+                ErrorKind::Parse,
+                format!("section {:?} lies outside of the file", s.name),
+            )));
+        }
+        sections.push(s);
+    }
+    if !cur.at_end() {
+        return Err(ContainerError::Toc(Error::new(
+            ErrorKind::Parse,
+            format!("{} unused bytes at end of table of contents", cur.remaining()),
+        )));
+    }
+    let mut by_offset:Vec<&SectionInfo> = sections.iter().collect();
+    by_offset.sort_by_key(|s| (s.offset, s.len));
+    if let Some(w) = by_offset.windows(2).find(|w| w[0].offset + w[0].len > w[1].offset) {
+        return Err(ContainerError::Toc(Error::new(
+            ErrorKind::Parse,
+            format!("sections {:?} and {:?} overlap", w[0].name, w[1].name),
+        )));
+    }
+    Ok(sections)
+}
+
+/// Reads the table of contents of a container written by [`ContainerWriter`], and gives random access to its sections.
+#[derive(Debug)]
+pub struct ContainerReader<R> {
+    inner: R,
+    sections: Vec<SectionInfo>,
+}
+
+impl<R: Read + Seek> ContainerReader<R> {
+    pub fn open(mut inner: R) -> Result<Self, ContainerError> {
+        fn check_magic(buf: &[u8]) -> Result<(), ContainerError> {
+            match ContainerMagic::byte_deserialize(&mut ByteCursor::new(buf)) {
+                Ok(()) => Ok(()),
+                Err(ParseOrIOError::Parse(e)) => Err(ContainerError::NotAContainer(e)),
+                Err(ParseOrIOError::IO(e)) => Err(ContainerError::IO(e.into())),
+            }
+        }
+
+        let mut header = [0; MAGIC_LEN];
+        inner.seek(SeekFrom::Start(0))?;
+        inner.read_exact(&mut header)?;
+        check_magic(&header)?;
+
+        let end = inner.seek(SeekFrom::End(0))?;
+        if end < (MAGIC_LEN + FOOTER_LEN) as u64 {
+            return Err(stdio::Error::from(stdio::ErrorKind::UnexpectedEof).into());
+        }
+        let footer_offset = end - FOOTER_LEN as u64;
+        let mut footer = [0; FOOTER_LEN];
+        inner.seek(SeekFrom::Start(footer_offset))?;
+        inner.read_exact(&mut footer)?;
+        check_magic(&footer[8..])?;
+        let toc_offset:u64 = NumberEncodingLE::byte_deserialize(&mut ByteCursor::new(&footer)).unwrap();
+        if toc_offset < MAGIC_LEN as u64 || toc_offset > footer_offset {
+            return Err(ContainerError::Toc(Error::new(
+                ErrorKind::Parse,
+                format!("table of contents offset {} lies outside of the file", toc_offset),
+            )));
+        }
+
+        let mut toc = vec![0; (footer_offset - toc_offset).try_into().unwrap()];
+        inner.seek(SeekFrom::Start(toc_offset))?;
+        inner.read_exact(&mut toc)?;
+        let sections = parse_toc(&toc, toc_offset)?;
+        Ok(Self{inner, sections})
+    }
+
+    pub fn sections(&self) -> &[SectionInfo] {
+        &self.sections
+    }
+
+    pub fn section(&self, name: &str) -> Option<&SectionInfo> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Opens the section called `name` as a reader bounded to its contents, after checking that it was written with `E`
+    pub fn open_section<E, T>(&mut self, name: &str) -> Result<Take<BufWrapper<&mut R>>, ContainerError>
+    where
+        E: ByteTypeId<T>,
+        T: ?Sized,
+    {
+        let s = self.sections.iter().find(|s| s.name == name).ok_or_else(|| ContainerError::NoSuchSection(name.to_string()))?;
+        let expected = type_id_of::<E, T>();
+        if s.type_id != expected {
+            return Err(ContainerError::TypeMismatch{section: s.name.clone(), expected, found: s.type_id.clone()});
+        }
+        let (offset, len) = (s.offset, s.len);
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let capacity = len.clamp(1, 8 * 1024) as usize;
+        Ok(Take::new(BufWrapper::with_capacity(capacity, &mut self.inner), len))
+    }
+
+    /// Decodes the whole of the section called `name` with `E`
+    pub fn read_section<E, T>(&mut self, name: &str) -> Result<T, ContainerError>
+    where
+        E: ByteDeserialize<T>,
+        E::ParseErr: StdError + Send + Sync + 'static,
+    {
+        let mut r = self.open_section::<E, T>(name)?;
+        let value = match E::byte_deserialize(&mut r) {
+            Ok(v) => v,
+            Err(ParseOrIOError::Parse(e)) => return Err(ContainerError::Payload(Error::new(ErrorKind::Parse, e))),
+            Err(ParseOrIOError::IO(e @ TakeError::LimitExceeded{..})) => return Err(ContainerError::Payload(Error::new(ErrorKind::UnexpectedEof, e))),
+            Err(ParseOrIOError::IO(TakeError::Inner(e))) => return Err(ContainerError::IO(e)),
+        };
+        let unused = r.limit();
+        if unused != 0 {
+            return Err(ContainerError::TrailingBytes{section: name.to_string(), unused});
+        }
+        Ok(value)
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use crate::endians::NumberEncodingBE as BE;
+    use crate::io::ByteRead;
+    use crate::lists::LengthPrefixList;
+
+    type Strings = LengthPrefixList<UVarInt, Str>;
+    type Nums = LengthPrefixList<UVarInt, BE>;
+
+    fn build() -> Vec<u8> {
+        let strings = vec!["alpha".to_string(), "beta".to_string()];
+        let nums:Vec<u32> = (0..1000).collect();
+        let mut w = ContainerWriter::create(Vec::new()).unwrap();
+        w.add_section::<Strings, _>("strings", &strings).unwrap();
+        w.add_section::<Nums, Vec<u32>>("index", &nums).unwrap();
+        w.add_section::<Str, str>("note", "hi").unwrap();
+        assert!(w.add_section::<Str, str>("note", "again").is_err());
+        w.finish().unwrap()
+    }
+
+    #[test]
+    fn blarg() {
+        let data = build();
+        let mut r = ContainerReader::open(Cursor::new(data)).unwrap();
+        assert_eq!(r.sections().iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["strings", "index", "note"]);
+        assert_eq!(r.section("note").unwrap().type_id, "twee::LengthPrefixedString<twee::UVarInt>");
+
+        // sections can be read in any order
+        assert_eq!(r.read_section::<Str, String>("note").unwrap(), "hi");
+        assert_eq!(r.read_section::<Nums, Vec<u32>>("index").unwrap(), (0..1000).collect::<Vec<u32>>());
+        assert_eq!(r.read_section::<Strings, Vec<String>>("strings").unwrap(), ["alpha", "beta"]);
+
+        // a section reader can't run into the next section
+        let mut s = r.open_section::<Nums, Vec<u32>>("index").unwrap();
+        assert_eq!(UVarInt::byte_deserialize(&mut s).unwrap(), 1000);
+        assert!(s.read_buf(4001).is_err());
+        drop(s);
+
+        assert!(matches!(r.open_section::<Strings, Vec<String>>("index"), Err(ContainerError::TypeMismatch{..})));
+        assert!(matches!(r.open_section::<Str, String>("nope"), Err(ContainerError::NoSuchSection(_))));
+    }
+
+    #[test]
+    fn damaged() {
+        let data = build();
+        assert!(matches!(ContainerReader::open(Cursor::new(&data[..data.len() - 1])), Err(ContainerError::NotAContainer(_))));
+        assert!(matches!(ContainerReader::open(Cursor::new(&data[1..])), Err(ContainerError::NotAContainer(_))));
+        assert!(matches!(ContainerReader::open(Cursor::new(&data[..5])), Err(ContainerError::IO(_))));
+
+        // point the table of contents somewhere else
+        let mut bad = data.clone();
+        let footer = bad.len() - FOOTER_LEN;
+        bad[footer] = bad[footer].wrapping_sub(1);
+        assert!(matches!(ContainerReader::open(Cursor::new(&bad)), Err(ContainerError::Toc(_) | ContainerError::UnsupportedVersion(_))));
+        bad[footer..footer + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(ContainerReader::open(Cursor::new(&bad)), Err(ContainerError::Toc(_))));
+
+        let toc = |sections: &[(&str, u64, u64)]| {
+            let mut toc = Vec::new();
+            UVarInt::byte_serialize(&CONTAINER_VERSION, &mut toc).unwrap();
+            UVarInt::byte_serialize(&(sections.len() as u64), &mut toc).unwrap();
+            for (name, offset, len) in sections {
+                Str::byte_serialize(*name, &mut toc).unwrap();
+                Str::byte_serialize("t", &mut toc).unwrap();
+                UVarInt::byte_serialize(offset, &mut toc).unwrap();
+                UVarInt::byte_serialize(len, &mut toc).unwrap();
+            }
+            parse_toc(&toc, 100)
+        };
+        assert_eq!(toc(&[("a", 10, 5), ("c", 15, 20), ("b", 15, 0)]).unwrap().len(), 3);
+        assert!(matches!(toc(&[("a", 10, 6), ("b", 15, 0)]), Err(ContainerError::Toc(_))));
+        assert!(matches!(toc(&[("a", 20, 10), ("b", 10, 11)]), Err(ContainerError::Toc(_))));
+        assert!(matches!(toc(&[("a", 90, 11)]), Err(ContainerError::Toc(_))));
+    }
+}
@@ -0,0 +1,270 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::io::ByteWrite;
+use crate::serde::ByteTypeId;
+
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+pub const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Continues a 64-bit FNV-1a hash from `hash` over `bytes`. Start from [`FNV_OFFSET_BASIS`].
+pub const fn fnv1a64(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// The 64-bit FNV-1a hash of `E`'s type id strings joined together
+pub fn type_fingerprint<E: ByteTypeId<T>, T: ?Sized>() -> u64 {
+    E::byte_type_id().iter().fold(FNV_OFFSET_BASIS, |hash, s| fnv1a64(hash, s.as_bytes()))
+}
+
+/// A type id string built at compile time out of pieces, so it can be hashed in a const context. See [`ByteConstTypeId`][crate::serde::ByteConstTypeId].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConstTypeId {
+    Str(&'static str),
+    /// The pieces one after another
+    Seq(&'static [ConstTypeId]),
+}
+
+impl ConstTypeId {
+    /// Continues an FNV-1a hash over the string
+    pub const fn hash_into(&self, hash: u64) -> u64 {
+        match self {
+            Self::Str(s) => fnv1a64(hash, s.as_bytes()),
+            Self::Seq(parts) => {
+                let mut hash = hash;
+                let mut i = 0;
+                while i < parts.len() {
+                    hash = parts[i].hash_into(hash);
+                    i += 1;
+                }
+                hash
+            },
+        }
+    }
+
+    /// Same as [`type_fingerprint`] of the string
+    pub const fn fingerprint(&self) -> u64 {
+        self.hash_into(FNV_OFFSET_BASIS)
+    }
+
+    /// The length of the string in bytes
+    pub const fn byte_len(&self) -> usize {
+        match self {
+            Self::Str(s) => s.len(),
+            Self::Seq(parts) => {
+                let mut len = 0;
+                let mut i = 0;
+                while i < parts.len() {
+                    len += parts[i].byte_len();
+                    i += 1;
+                }
+                len
+            },
+        }
+    }
+
+    /// The string's pieces in order, as [`ByteTypeId::byte_type_id`] returns them
+    pub fn pieces(&self) -> Vec<&'static str> {
+        let mut res = Vec::new();
+        self.push_pieces(&mut res);
+        res
+    }
+
+    fn push_pieces(&self, res: &mut Vec<&'static str>) {
+        match self {
+            Self::Str(s) => res.push(s),
+            Self::Seq(parts) => parts.iter().for_each(|p| p.push_pieces(res)),
+        }
+    }
+
+    /// Writes out the string's bytes, without a length
+    pub fn write_to<W: ByteWrite>(&self, io: &mut W) -> Result<(), W::Err> {
+        match self {
+            Self::Str(s) => io.write_buf(s.as_bytes()),
+            Self::Seq(parts) => parts.iter().try_for_each(|p| p.write_to(io)),
+        }
+    }
+}
+
+impl fmt::Display for ConstTypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(s) => f.write_str(s),
+            Self::Seq(parts) => parts.iter().try_for_each(|p| p.fmt(f)),
+        }
+    }
+}
+
+
+/// What sort of encoding a [`TypeDescriptor`] node is
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TypeKind {
+    /// Fixed-width numbers
+    Number,
+    /// Variable-width numbers
+    VarInt,
+    String,
+    List,
+    /// Constant bytes, or one of several
+    Magic,
+    /// A single value with extra framing around it, like a length or a fingerprint
+    Wrapper,
+    /// Anything that only gives a type id string; the whole string is the name
+    Opaque,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeParam {
+    Type(TypeDescriptor),
+    /// Anything that isn't an encoder, like a list length or a number type
+    Value(Cow<'static, str>),
+}
+
+impl fmt::Display for TypeParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Type(t) => t.fmt(f),
+            Self::Value(v) => f.write_str(v),
+        }
+    }
+}
+
+/// A tree-shaped form of [`ByteTypeId::byte_type_id`][crate::serde::ByteTypeId::byte_type_id]. Its [`Display`][fmt::Display] impl writes exactly the same string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeDescriptor {
+    pub kind: TypeKind,
+    pub name: Cow<'static, str>,
+    /// Written after the name between `<` and `>`, separated by commas, unless there are none
+    pub params: Vec<TypeParam>,
+    /// How many bytes every value takes up, if that's always the same
+    pub fixed_size: Option<u64>,
+}
+
+impl TypeDescriptor {
+    pub fn new(kind: TypeKind, name: impl Into<Cow<'static, str>>, params: Vec<TypeParam>) -> Self {
+        Self{
+            kind,
+            name: name.into(),
+            params,
+            fixed_size: None,
+        }
+    }
+
+    pub fn leaf(kind: TypeKind, name: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(kind, name, Vec::new())
+    }
+
+    pub fn opaque(type_id: String) -> Self {
+        Self::leaf(TypeKind::Opaque, type_id)
+    }
+
+    pub fn with_fixed_size(mut self, size: impl Into<Option<u64>>) -> Self {
+        self.fixed_size = size.into();
+        self
+    }
+
+    /// The descriptors of this node's encoder parameters, skipping value parameters
+    pub fn children(&self) -> impl Iterator<Item = &TypeDescriptor> {
+        self.params.iter().filter_map(|p| match p {
+            TypeParam::Type(t) => Some(t),
+            TypeParam::Value(_) => None,
+        })
+    }
+}
+
+impl fmt::Display for TypeDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.params.is_empty() {
+            f.write_str("<")?;
+            for (i, param) in self.params.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(",")?;
+                }
+                param.fmt(f)?;
+            }
+            f.write_str(">")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serde::ByteConstTypeId;
+    use crate::byte_length::ByteLengthPrefixed;
+    use crate::block_list::{BlockList, CountAndSize};
+    use crate::const_list::ConstListEncoder;
+    use crate::endians::{NumberEncodingBE as BE, NumberEncodingLE as LE, NumberEncodingSingleByte};
+    use crate::envelope::{Envelope, Verbose};
+    use crate::lists::LengthPrefixList;
+    use crate::magic_bytes::{magic_bytes_type, magic_enum};
+    use crate::string::LengthPrefixString;
+    use crate::varint::{SVarInt, UVarInt};
+
+    fn check<E: ByteConstTypeId<T>, T: ?Sized>() -> TypeDescriptor {
+        let type_id = E::byte_type_id().concat();
+        let desc = E::type_descriptor();
+        assert_eq!(desc.to_string(), type_id);
+        assert_eq!(E::TYPE_ID.pieces(), E::byte_type_id());
+        assert_eq!(E::TYPE_ID.byte_len(), type_id.len());
+        assert_eq!(E::TYPE_FINGERPRINT, type_fingerprint::<E, T>());
+        desc
+    }
+
+    #[test]
+    fn blarg() {
+        magic_bytes_type!{
+            struct Header[0x69, b'x'];
+        }
+        magic_enum!{
+            enum Kind {
+                A = [b'a', 0],
+                B = [b'b', 0] & [0xff, 0],
+            }
+            struct KindMagic;
+        }
+
+        check::<UVarInt, u64>();
+        check::<SVarInt, i64>();
+        check::<NumberEncodingSingleByte, u8>();
+        check::<LE, u8>();
+        check::<BE, f64>();
+        check::<KindMagic, Kind>();
+        check::<Header, ()>();
+        check::<LengthPrefixString<UVarInt>, str>();
+        check::<Envelope<LengthPrefixList<UVarInt, BE>, Verbose>, Vec<u32>>();
+        check::<ByteLengthPrefixed<LE, LengthPrefixString<UVarInt>>, String>();
+        check::<BlockList<UVarInt, SVarInt, CountAndSize>, [i64]>();
+
+        let desc = check::<ConstListEncoder<ConstListEncoder<LE>>, [[u32; 3]; 2]>();
+        assert_eq!(desc.kind, TypeKind::List);
+        assert_eq!(desc.fixed_size, Some(24));
+        assert_eq!(desc.children().next().unwrap().fixed_size, Some(12));
+        assert_eq!(check::<KindMagic, Kind>().fixed_size, Some(2));
+
+        let desc = check::<LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>, Vec<String>>();
+        assert_eq!(desc.fixed_size, None);
+        let kinds:Vec<_> = desc.children().map(|c| c.kind).collect();
+        assert_eq!(kinds, [TypeKind::VarInt, TypeKind::String]);
+    }
+
+    #[test]
+    fn const_fingerprints() {
+        type Msg = Envelope<LengthPrefixList<UVarInt, ConstListEncoder<BE>>>;
+        const FINGERPRINT: u64 = <Msg as ByteConstTypeId<Vec<[u16; 12]>>>::TYPE_FINGERPRINT;
+        assert_eq!(FINGERPRINT, type_fingerprint::<Msg, Vec<[u16; 12]>>());
+        assert_eq!(
+            <Msg as ByteConstTypeId<Vec<[u16; 12]>>>::TYPE_ID.to_string(),
+            "twee::Envelope<twee::LengthPrefixedList<twee::UVarInt,twee::ConstSizeList<twee::BE<u16>,12>>>",
+        );
+        assert_eq!(ConstTypeId::Str("").fingerprint(), FNV_OFFSET_BASIS);
+    }
+}
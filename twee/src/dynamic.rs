@@ -0,0 +1,735 @@
+use std::fmt;
+
+use crate::descriptor::{fnv1a64, TypeDescriptor, TypeKind, TypeParam, FNV_OFFSET_BASIS};
+use crate::endians::{Endianness, NumberEncodingLE};
+use crate::envelope::SchemaMismatch;
+use crate::io::{ByteCursor, ByteRead, EndOfBufferError};
+use crate::serde::{ByteDeserialize, ParseOrIOError};
+use crate::varint::{SVarInt, UVarInt, VarIntTooBig};
+
+/// A type id string that couldn't be turned into a [`TypeDescriptor`] or a [`DynamicDecoder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeIdError {
+    /// The string doesn't follow the type id grammar at byte offset `pos`
+    Syntax{pos: usize, expected: &'static str},
+    /// Not one of the encoders in this crate, or an opaque descriptor
+    UnknownType{name: String},
+    /// A known type given a parameter it can't take
+    BadParam{name: String, param: String},
+}
+
+impl fmt::Display for TypeIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax{pos, expected} => write!(f, "invalid type id at offset {}: expected {}", pos, expected),
+            Self::UnknownType{name} => write!(f, "unknown type {:?}", name),
+            Self::BadParam{name, param} => write!(f, "invalid parameter {:?} for {}", param, name),
+        }
+    }
+}
+
+impl std::error::Error for TypeIdError {}
+
+/// How deeply encoders may be nested in a type id or descriptor
+pub const MAX_TYPE_DEPTH: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum NumberType { U8, U16, U32, U64, U128, I8, I16, I32, I64, I128, F32, F64 }
+
+impl NumberType {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "u8" => Self::U8,
+            "u16" => Self::U16,
+            "u32" => Self::U32,
+            "u64" => Self::U64,
+            "u128" => Self::U128,
+            "i8" => Self::I8,
+            "i16" => Self::I16,
+            "i32" => Self::I32,
+            "i64" => Self::I64,
+            "i128" => Self::I128,
+            "f32" => Self::F32,
+            "f64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> u64 {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+            Self::U128 | Self::I128 => 16,
+        }
+    }
+
+    fn is_unsigned(self) -> bool {
+        matches!(self, Self::U8 | Self::U16 | Self::U32 | Self::U64 | Self::U128)
+    }
+
+    fn value(self, bytes: &[u8], endianness: Endianness) -> Value {
+        macro_rules! num {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().unwrap();
+                match endianness {
+                    Endianness::Little => <$t>::from_le_bytes(bytes),
+                    Endianness::Big => <$t>::from_be_bytes(bytes),
+                }
+            }};
+        }
+        match self {
+            Self::U8 => Value::UInt(num!(u8).into()),
+            Self::U16 => Value::UInt(num!(u16).into()),
+            Self::U32 => Value::UInt(num!(u32).into()),
+            Self::U64 => Value::UInt(num!(u64).into()),
+            Self::U128 => Value::UInt(num!(u128)),
+            Self::I8 => Value::Int(num!(i8).into()),
+            Self::I16 => Value::Int(num!(i16).into()),
+            Self::I32 => Value::Int(num!(i32).into()),
+            Self::I64 => Value::Int(num!(i64).into()),
+            Self::I128 => Value::Int(num!(i128)),
+            Self::F32 => Value::Float(num!(f32).into()),
+            Self::F64 => Value::Float(num!(f64)),
+        }
+    }
+}
+
+/// A pattern of a magic type, with a mask of all ones if it had none
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+impl Pattern {
+    fn matches(&self, found: &[u8]) -> bool {
+        self.bytes.iter().zip(&self.mask).zip(found).all(|((b, m), f)| b & m == f & m)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self{s, pos: 0, depth: 0}
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn syntax<T>(&self, expected: &'static str) -> Result<T, TypeIdError> {
+        Err(TypeIdError::Syntax{pos: self.pos, expected})
+    }
+
+    fn try_eat(&mut self, c: char) -> bool {
+        let found = self.rest().starts_with(c);
+        if found {
+            self.pos += c.len_utf8();
+        }
+        found
+    }
+
+    fn eat(&mut self, c: char, expected: &'static str) -> Result<(), TypeIdError> {
+        if self.try_eat(c) {
+            Ok(())
+        } else {
+            self.syntax(expected)
+        }
+    }
+
+    fn end(&self) -> Result<(), TypeIdError> {
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            self.syntax("end of type id")
+        }
+    }
+
+    /// Everything up to the next `<`, `,` or `>`
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(['<', ',', '>']).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn type_param(&mut self) -> Result<TypeParam, TypeIdError> {
+        self.parse_type().map(TypeParam::Type)
+    }
+
+    /// Parses `<` followed by `count` encoders separated by commas, and `>`
+    fn type_params(&mut self, count: usize) -> Result<Vec<TypeParam>, TypeIdError> {
+        self.eat('<', "\"<\"")?;
+        let mut params = Vec::with_capacity(count);
+        for i in 0..count {
+            if i != 0 {
+                self.eat(',', "\",\"")?;
+            }
+            params.push(self.type_param()?);
+        }
+        self.eat('>', "\">\"")?;
+        Ok(params)
+    }
+
+    fn parse_type(&mut self) -> Result<TypeDescriptor, TypeIdError> {
+        if self.depth == MAX_TYPE_DEPTH {
+            return self.syntax("fewer levels of nesting");
+        }
+        self.depth += 1;
+        let name = self.word();
+        if name.is_empty() {
+            return self.syntax("type name");
+        }
+        let bad_param = |param: &str| TypeIdError::BadParam{name: name.to_owned(), param: param.to_owned()};
+        let desc = match name {
+            "twee::UVarInt" | "twee::SVarInt" => TypeDescriptor::leaf(TypeKind::VarInt, name.to_owned()),
+            "twee::SingleByteUnsigned" | "twee::SingleByteSigned" => TypeDescriptor::leaf(TypeKind::Number, name.to_owned()).with_fixed_size(1),
+            "twee::LE" | "twee::BE" => {
+                self.eat('<', "\"<\"")?;
+                let number = self.word();
+                let size = NumberType::from_name(number).ok_or_else(|| bad_param(number))?.size();
+                self.eat('>', "\">\"")?;
+                TypeDescriptor::new(TypeKind::Number, name.to_owned(), vec![TypeParam::Value(number.to_owned().into())]).with_fixed_size(size)
+            },
+            "twee::LengthPrefixedList" | "twee::BlockList" | "twee::SizedBlockList" => TypeDescriptor::new(TypeKind::List, name.to_owned(), self.type_params(2)?),
+            "twee::ConstSizeList" => {
+                self.eat('<', "\"<\"")?;
+                let inner = self.parse_type()?;
+                self.eat(',', "\",\"")?;
+                let len = self.word();
+                let n:u64 = len.parse().map_err(|_| bad_param(len))?;
+                self.eat('>', "\">\"")?;
+                let fixed_size = inner.fixed_size.map(|size| size.checked_mul(n).ok_or_else(|| bad_param(len))).transpose()?;
+                TypeDescriptor::new(TypeKind::List, name.to_owned(), vec![
+                    TypeParam::Type(inner),
+                    TypeParam::Value(len.to_owned().into()),
+                ]).with_fixed_size(fixed_size)
+            },
+            "twee::LengthPrefixedString" => TypeDescriptor::new(TypeKind::String, name.to_owned(), self.type_params(1)?),
+            "twee::ByteLengthPrefixed" => TypeDescriptor::new(TypeKind::Wrapper, name.to_owned(), self.type_params(2)?),
+            "twee::Envelope" | "twee::VerboseEnvelope" => TypeDescriptor::new(TypeKind::Wrapper, name.to_owned(), self.type_params(1)?),
+            "MagicBytes" | "EndianMagicBytes" | "MagicEnum" => {
+                self.eat('<', "\"<\"")?;
+                let raw_start = self.pos;
+                let patterns = self.magic_patterns(name)?;
+                let raw = &self.s[raw_start..self.pos];
+                self.eat('>', "\">\"")?;
+                let size = patterns[0].bytes.len();
+                if patterns.iter().any(|p| p.bytes.len() != size) {
+                    return Err(bad_param(raw));
+                }
+                TypeDescriptor::new(TypeKind::Magic, name.to_owned(), vec![TypeParam::Value(raw.to_owned().into())]).with_fixed_size(size as u64)
+            },
+            _ => return Err(TypeIdError::UnknownType{name: name.to_owned()}),
+        };
+        self.depth -= 1;
+        Ok(desc)
+    }
+
+    /// Parses the inside of `MagicBytes<...>`, `EndianMagicBytes<...>` or `MagicEnum<...>`, up to the closing `>` or the end
+    fn magic_patterns(&mut self, name: &str) -> Result<Vec<Pattern>, TypeIdError> {
+        let exact = |bytes: Vec<u8>| Pattern{mask: vec![0xff; bytes.len()], bytes};
+        match name {
+            "MagicBytes" => return Ok(vec![exact(self.byte_list('>')?)]),
+            "EndianMagicBytes" => {
+                let little = self.byte_list(';')?;
+                self.eat(';', "\";\"")?;
+                let big = self.byte_list('>')?;
+                return Ok(vec![exact(little), exact(big)]);
+            },
+            _ => (),
+        }
+        let mut patterns = Vec::new();
+        while !self.rest().is_empty() && !self.rest().starts_with('>') {
+            self.eat('[', "\"[\"")?;
+            let bytes = self.byte_list(']')?;
+            self.eat(']', "\"]\"")?;
+            let mask = if self.try_eat('&') {
+                self.eat('[', "\"[\"")?;
+                let mask_pos = self.pos;
+                let mask = self.byte_list(']')?;
+                if mask.len() != bytes.len() {
+                    return Err(TypeIdError::Syntax{pos: mask_pos, expected: "a mask as long as its pattern"});
+                }
+                self.eat(']', "\"]\"")?;
+                mask
+            } else {
+                vec![0xff; bytes.len()]
+            };
+            self.eat(',', "\",\"")?;
+            patterns.push(Pattern{bytes, mask});
+        }
+        if patterns.is_empty() {
+            return self.syntax("a magic pattern");
+        }
+        Ok(patterns)
+    }
+
+    /// Byte literals each followed by a comma, up to `close`
+    fn byte_list(&mut self, close: char) -> Result<Vec<u8>, TypeIdError> {
+        let mut bytes = Vec::new();
+        while !self.rest().is_empty() && !self.rest().starts_with(close) {
+            bytes.push(self.byte_literal()?);
+            self.eat(',', "\",\"")?;
+        }
+        Ok(bytes)
+    }
+
+    /// A Rust integer or byte literal, as written by `stringify!`
+    fn byte_literal(&mut self) -> Result<u8, TypeIdError> {
+        let rest = self.rest();
+        if let Some(body) = rest.strip_prefix("b'") {
+            let mut chars = body.char_indices();
+            let byte = match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => Some(b'\n'),
+                    Some((_, 'r')) => Some(b'\r'),
+                    Some((_, 't')) => Some(b'\t'),
+                    Some((_, '0')) => Some(0),
+                    Some((_, c @ ('\\' | '\'' | '"'))) => Some(c as u8),
+                    Some((i, 'x')) => body.get(i + 1..i + 3).and_then(|hex| {
+                        chars.nth(1);
+                        u8::from_str_radix(hex, 16).ok()
+                    }),
+                    _ => None,
+                },
+                Some((_, c)) if c.is_ascii() && c != '\'' => Some(c as u8),
+                _ => None,
+            };
+            return match (byte, chars.next()) {
+                (Some(byte), Some((i, '\''))) => {
+                    self.pos += 2 + i + 1;
+                    Ok(byte)
+                },
+                _ => self.syntax("byte literal"),
+            };
+        }
+
+        let len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+        let literal = rest[..len].replace('_', "");
+        let literal = literal.strip_suffix("u8").unwrap_or(&literal);
+        let (digits, radix) = match literal.get(..2) {
+            Some("0x") => (&literal[2..], 16),
+            Some("0o") => (&literal[2..], 8),
+            Some("0b") => (&literal[2..], 2),
+            _ => (literal, 10),
+        };
+        match u8::from_str_radix(digits, radix) {
+            Ok(byte) if !digits.is_empty() => {
+                self.pos += len;
+                Ok(byte)
+            },
+            _ => self.syntax("byte literal"),
+        }
+    }
+}
+
+/// Parses a type id string back into the [`TypeDescriptor`] the encoder that wrote it would give.
+///
+/// Only the encoders in this crate are known; anything else is a [`TypeIdError::UnknownType`].
+pub fn parse_type_id(type_id: &str) -> Result<TypeDescriptor, TypeIdError> {
+    let mut parser = Parser::new(type_id);
+    let desc = parser.parse_type()?;
+    parser.end()?;
+    Ok(desc)
+}
+
+/// A value read by a [`DynamicDecoder`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    UInt(u128),
+    Int(i128),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    /// A list of `u8`s
+    Bytes(Vec<u8>),
+    /// Which pattern of the magic type matched, in the order of its type id, and the bytes read
+    Magic{variant: usize, bytes: Vec<u8>},
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicError {
+    VarIntTooBig,
+    /// A length doesn't fit in a `u64`
+    LengthTooBig(u128),
+    InvalidUtf8(std::string::FromUtf8Error),
+    MagicMismatch{expected: String, found: Vec<u8>},
+    /// A byte length prefixed value or sized block claims to run past the end of its `len` bytes
+    Overrun{len: u64},
+    /// A byte length prefixed value was parsed without using all of its bytes
+    TrailingBytes{unused: u64},
+    /// A sized block's items didn't take up the number of bytes its header said
+    BlockSizeMismatch{expected: u64, found: u64},
+    SchemaMismatch(SchemaMismatch),
+}
+
+impl fmt::Display for DynamicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VarIntTooBig => VarIntTooBig.fmt(f),
+            Self::LengthTooBig(len) => write!(f, "length {} is too big", len),
+            Self::InvalidUtf8(e) => write!(f, "invalid string: {}", e),
+            Self::MagicMismatch{expected, found} => write!(f, "magic bytes mismatch: expecting one of {}, found {:?}", expected, found),
+            Self::Overrun{len} => write!(f, "value runs past the end of its {} bytes", len),
+            Self::TrailingBytes{unused} => write!(f, "{} unused bytes after value", unused),
+            Self::BlockSizeMismatch{expected, found} => write!(f, "block of {} bytes took up {}", expected, found),
+            Self::SchemaMismatch(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DynamicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidUtf8(e) => Some(e),
+            Self::SchemaMismatch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+type DynamicResult<T, R> = Result<T, ParseOrIOError<DynamicError, <R as ByteRead>::Err>>;
+
+/// Turns the end of a bounded buffer into `err`
+fn bounded<T>(res: DynamicResult<T, ByteCursor<'_>>, err: DynamicError) -> Result<T, DynamicError> {
+    res.map_err(|e| match e {
+        ParseOrIOError::Parse(e) => e,
+        ParseOrIOError::IO(EndOfBufferError) => err,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    UVarInt,
+    SVarInt,
+    Number{number: NumberType, endianness: Endianness},
+    List{length: Box<Node>, item: Box<Node>},
+    ConstList{item: Box<Node>, len: u64},
+    String{length: Box<Node>},
+    ByteLength{length: Box<Node>, inner: Box<Node>},
+    BlockList{length: Box<Node>, item: Box<Node>, sized: bool},
+    Envelope{inner: Box<Node>, verbose: bool, fingerprint: u64, type_id: String},
+    Magic{patterns: Vec<Pattern>, raw: String},
+}
+
+impl Node {
+    fn compile(desc: &TypeDescriptor) -> Result<Self, TypeIdError> {
+        Self::compile_at(desc, 1)
+    }
+
+    fn compile_at(desc: &TypeDescriptor, depth: usize) -> Result<Self, TypeIdError> {
+        let name = &*desc.name;
+        let bad_params = || TypeIdError::BadParam{
+            name: name.to_owned(),
+            param: desc.params.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(","),
+        };
+        if depth > MAX_TYPE_DEPTH {
+            return Err(bad_params());
+        }
+        let types:Vec<_> = desc.children().collect();
+        let values:Vec<_> = desc.params.iter().filter_map(|p| match p {
+            TypeParam::Value(v) => Some(&**v),
+            TypeParam::Type(_) => None,
+        }).collect();
+        let node = |i: usize| types.get(i).copied().ok_or_else(bad_params).and_then(|t| Self::compile_at(t, depth + 1)).map(Box::new);
+        let length = |i: usize| node(i).and_then(|n| if n.is_unsigned() { Ok(n) } else { Err(bad_params()) });
+        let (expect_types, expect_values, node) = match name {
+            "twee::UVarInt" => (0, 0, Self::UVarInt),
+            "twee::SVarInt" => (0, 0, Self::SVarInt),
+            "twee::SingleByteUnsigned" => (0, 0, Self::Number{number: NumberType::U8, endianness: Endianness::Little}),
+            "twee::SingleByteSigned" => (0, 0, Self::Number{number: NumberType::I8, endianness: Endianness::Little}),
+            "twee::LE" | "twee::BE" => {
+                let number = values.first().and_then(|v| NumberType::from_name(v)).ok_or_else(bad_params)?;
+                let endianness = if name == "twee::LE" { Endianness::Little } else { Endianness::Big };
+                (0, 1, Self::Number{number, endianness})
+            },
+            "twee::LengthPrefixedList" => (2, 0, Self::List{length: length(0)?, item: node(1)?}),
+            "twee::ConstSizeList" => {
+                let len = values.first().and_then(|v| v.parse().ok()).ok_or_else(bad_params)?;
+                (1, 1, Self::ConstList{item: node(0)?, len})
+            },
+            "twee::LengthPrefixedString" => (1, 0, Self::String{length: length(0)?}),
+            "twee::ByteLengthPrefixed" => (2, 0, Self::ByteLength{length: length(0)?, inner: node(1)?}),
+            "twee::BlockList" | "twee::SizedBlockList" => (2, 0, Self::BlockList{length: length(0)?, item: node(1)?, sized: name == "twee::SizedBlockList"}),
+            "twee::Envelope" | "twee::VerboseEnvelope" => {
+                let type_id = types.first().ok_or_else(bad_params)?.to_string();
+                (1, 0, Self::Envelope{
+                    inner: node(0)?,
+                    verbose: name == "twee::VerboseEnvelope",
+                    fingerprint: fnv1a64(FNV_OFFSET_BASIS, type_id.as_bytes()),
+                    type_id,
+                })
+            },
+            "MagicBytes" | "EndianMagicBytes" | "MagicEnum" => {
+                let raw = *values.first().ok_or_else(bad_params)?;
+                let mut parser = Parser::new(raw);
+                let patterns = parser.magic_patterns(name).map_err(|_| bad_params())?;
+                parser.end().map_err(|_| bad_params())?;
+                if patterns.iter().any(|p| p.bytes.len() != patterns[0].bytes.len()) {
+                    return Err(bad_params());
+                }
+                (0, 1, Self::Magic{patterns, raw: raw.to_owned()})
+            },
+            _ => return Err(TypeIdError::UnknownType{name: desc.to_string()}),
+        };
+        if types.len() != expect_types || values.len() != expect_values {
+            return Err(bad_params());
+        }
+        Ok(node)
+    }
+
+    /// Whether this always decodes to a [`Value::UInt`], so it can encode lengths
+    fn is_unsigned(&self) -> bool {
+        match self {
+            Self::UVarInt => true,
+            Self::Number{number, ..} => number.is_unsigned(),
+            _ => false,
+        }
+    }
+
+    fn is_byte(&self) -> bool {
+        matches!(self, Self::Number{number: NumberType::U8, ..})
+    }
+
+    fn list(&self, items: Vec<Value>) -> Value {
+        if self.is_byte() {
+            Value::Bytes(items.into_iter().map(|v| match v {
+                Value::UInt(b) => b as u8,
+                _ => unreachable!(),
+            }).collect())
+        } else {
+            Value::List(items)
+        }
+    }
+
+    fn decode_length<R: ByteRead>(&self, io: &mut R) -> DynamicResult<u64, R> {
+        match self.decode(io)? {
+            Value::UInt(len) => len.try_into().map_err(|_| ParseOrIOError::Parse(DynamicError::LengthTooBig(len))),
+            _ => unreachable!("lengths are checked to be unsigned when compiling"),
+        }
+    }
+
+    fn decode_string<R: ByteRead>(&self, io: &mut R) -> DynamicResult<String, R> {
+        let len = self.decode_length(io)?;
+        let buf = io.read_buf(len).map_err(ParseOrIOError::IO)?;
+        String::from_utf8(buf.into_owned()).map_err(|e| ParseOrIOError::Parse(DynamicError::InvalidUtf8(e)))
+    }
+
+    fn decode_items<R: ByteRead>(&self, io: &mut R, count: u64, items: &mut Vec<Value>) -> DynamicResult<(), R> {
+        for _ in 0..count {
+            items.push(self.decode(io)?);
+        }
+        Ok(())
+    }
+
+    fn decode<R: ByteRead>(&self, io: &mut R) -> DynamicResult<Value, R> {
+        let varint = |e: ParseOrIOError<VarIntTooBig, R::Err>| e.map_parse(|VarIntTooBig| DynamicError::VarIntTooBig);
+        Ok(match self {
+            Self::UVarInt => Value::UInt(<UVarInt as ByteDeserialize<u64>>::byte_deserialize(io).map_err(varint)?.into()),
+            Self::SVarInt => Value::Int(<SVarInt as ByteDeserialize<i64>>::byte_deserialize(io).map_err(varint)?.into()),
+            Self::Number{number, endianness} => {
+                let buf = io.read_buf(number.size()).map_err(ParseOrIOError::IO)?;
+                number.value(&buf, *endianness)
+            },
+            Self::List{length, item} => {
+                let len = length.decode_length(io)?;
+                let mut items = Vec::new();
+                item.decode_items(io, len, &mut items)?;
+                item.list(items)
+            },
+            Self::ConstList{item, len} => {
+                let mut items = Vec::new();
+                item.decode_items(io, *len, &mut items)?;
+                item.list(items)
+            },
+            Self::String{length} => Value::String(length.decode_string(io)?),
+            Self::ByteLength{length, inner} => {
+                let len = length.decode_length(io)?;
+                let buf = io.read_buf(len).map_err(ParseOrIOError::IO)?;
+                let mut cur = ByteCursor::new(&buf);
+                let value = bounded(inner.decode(&mut cur), DynamicError::Overrun{len}).map_err(ParseOrIOError::Parse)?;
+                if !cur.at_end() {
+                    return Err(ParseOrIOError::Parse(DynamicError::TrailingBytes{unused: cur.remaining() as u64}));
+                }
+                value
+            },
+            Self::BlockList{length, item, sized} => {
+                let mut items = Vec::new();
+                loop {
+                    let count = length.decode_length(io)?;
+                    if count == 0 {
+                        break;
+                    }
+                    if !*sized {
+                        item.decode_items(io, count, &mut items)?;
+                        continue;
+                    }
+                    let size = length.decode_length(io)?;
+                    let buf = io.read_buf(size).map_err(ParseOrIOError::IO)?;
+                    let mut cur = ByteCursor::new(&buf);
+                    bounded(item.decode_items(&mut cur, count, &mut items), DynamicError::Overrun{len: size}).map_err(ParseOrIOError::Parse)?;
+                    if !cur.at_end() {
+                        return Err(ParseOrIOError::Parse(DynamicError::BlockSizeMismatch{expected: size, found: cur.position() as u64}));
+                    }
+                }
+                item.list(items)
+            },
+            Self::Envelope{inner, verbose, fingerprint, type_id} => {
+                let found:u64 = NumberEncodingLE::byte_deserialize(io).map_err(|e| e.map_parse(|n| match n {}))?;
+                let found_type_id = if *verbose {
+                    Some(Self::UVarInt.decode_string(io)?)
+                } else {
+                    None
+                };
+                if found != *fingerprint {
+                    return Err(ParseOrIOError::Parse(DynamicError::SchemaMismatch(SchemaMismatch{
+                        expected: *fingerprint,
+                        found,
+                        expected_type_id: type_id.clone(),
+                        found_type_id,
+                    })));
+                }
+                inner.decode(io)?
+            },
+            Self::Magic{patterns, raw} => {
+                let buf = io.read_buf(patterns[0].bytes.len() as u64).map_err(ParseOrIOError::IO)?;
+                match patterns.iter().position(|p| p.matches(&buf)) {
+                    Some(variant) => Value::Magic{variant, bytes: buf.into_owned()},
+                    None => return Err(ParseOrIOError::Parse(DynamicError::MagicMismatch{expected: raw.clone(), found: buf.into_owned()})),
+                }
+            },
+        })
+    }
+}
+
+/// Decodes data into [`Value`]s going only by a type id string, for tools that don't have the types that wrote it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicDecoder {
+    descriptor: TypeDescriptor,
+    root: Node,
+}
+
+impl DynamicDecoder {
+    pub fn parse(type_id: &str) -> Result<Self, TypeIdError> {
+        Self::from_descriptor(parse_type_id(type_id)?)
+    }
+
+    pub fn from_descriptor(descriptor: TypeDescriptor) -> Result<Self, TypeIdError> {
+        let root = Node::compile(&descriptor)?;
+        Ok(Self{descriptor, root})
+    }
+
+    pub fn descriptor(&self) -> &TypeDescriptor {
+        &self.descriptor
+    }
+
+    pub fn decode<R: ByteRead>(&self, io: &mut R) -> Result<Value, ParseOrIOError<DynamicError, R::Err>> {
+        self.root.decode(io)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serde::ByteSerialize;
+    use crate::block_list::{BlockList, CountAndSize};
+    use crate::byte_length::ByteLengthPrefixed;
+    use crate::const_list::ConstListEncoder;
+    use crate::endians::{NumberEncodingBE as BE, NumberEncodingLE as LE};
+    use crate::envelope::{Envelope, Verbose};
+    use crate::lists::LengthPrefixList;
+    use crate::magic_bytes::{magic_bytes_type, magic_enum};
+    use crate::string::LengthPrefixString;
+
+    fn check<E: ByteSerialize<T>, T: ?Sized>(item: &T, expected: Value) {
+        let type_id = E::byte_type_id().concat();
+        assert_eq!(parse_type_id(&type_id).unwrap(), E::type_descriptor());
+        let decoder = DynamicDecoder::parse(&type_id).unwrap();
+        let mut data = Vec::new();
+        E::byte_serialize(item, &mut data).unwrap();
+        let mut cur = ByteCursor::new(&data);
+        assert_eq!(decoder.decode(&mut cur).unwrap(), expected);
+        assert!(cur.at_end());
+    }
+
+    #[test]
+    fn blarg() {
+        magic_bytes_type!{
+            struct Header[0x69, b'<', b'\x00'];
+        }
+        magic_enum!{
+            enum Kind {
+                A = [b'a', 0],
+                B = [b'b', 0] & [0xff, 0],
+            }
+            struct KindMagic;
+        }
+        magic_bytes_type!{
+            struct TiffOrder {
+                little: [b'I', b'I'],
+                big: [b'M', b'M'],
+            }
+        }
+
+        check::<LE, u32>(&7, Value::UInt(7));
+        check::<BE, f32>(&1.5, Value::Float(1.5));
+        check::<SVarInt, i64>(&-300, Value::Int(-300));
+        check::<Header, ()>(&(), Value::Magic{variant: 0, bytes: vec![0x69, b'<', 0]});
+        check::<KindMagic, Kind>(&Kind::B, Value::Magic{variant: 1, bytes: vec![b'b', 0]});
+        check::<TiffOrder, Endianness>(&Endianness::Big, Value::Magic{variant: 1, bytes: b"MM".to_vec()});
+        check::<LengthPrefixList<UVarInt, LE>, Vec<u8>>(&vec![1, 2], Value::Bytes(vec![1, 2]));
+        check::<ConstListEncoder<BE>, [i16; 2]>(&[-1, 1], Value::List(vec![Value::Int(-1), Value::Int(1)]));
+        check::<Envelope<ByteLengthPrefixed<LE, LengthPrefixString<UVarInt>>, Verbose>, String>(
+            &"hi".to_owned(),
+            Value::String("hi".to_owned()),
+        );
+        check::<BlockList<UVarInt, LengthPrefixList<UVarInt, SVarInt>, CountAndSize>, [Vec<i64>]>(
+            &[vec![-1], vec![]],
+            Value::List(vec![Value::List(vec![Value::Int(-1)]), Value::List(vec![])]),
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            DynamicDecoder::parse("twee::LengthPrefixedList<twee::UVarInt,my::Thing<u8>>").unwrap_err(),
+            TypeIdError::UnknownType{name: "my::Thing".to_owned()},
+        );
+        assert_eq!(
+            parse_type_id("twee::LengthPrefixedString<twee::SVarInt>").and_then(DynamicDecoder::from_descriptor).unwrap_err(),
+            TypeIdError::BadParam{name: "twee::LengthPrefixedString".to_owned(), param: "twee::SVarInt".to_owned()},
+        );
+        assert!(matches!(parse_type_id("twee::LE<u9>"), Err(TypeIdError::BadParam{..})));
+        assert_eq!(parse_type_id("twee::UVarInt>"), Err(TypeIdError::Syntax{pos: 13, expected: "end of type id"}));
+        assert!(matches!(
+            parse_type_id(&"twee::Envelope<".repeat(200_000)),
+            Err(TypeIdError::Syntax{expected: "fewer levels of nesting", ..}),
+        ));
+        assert_eq!(
+            parse_type_id("twee::ConstSizeList<twee::LE<u64>,18446744073709551615>"),
+            Err(TypeIdError::BadParam{name: "twee::ConstSizeList".to_owned(), param: "18446744073709551615".to_owned()}),
+        );
+        assert_eq!(
+            DynamicDecoder::from_descriptor(TypeDescriptor::opaque("my::Thing".to_owned())).unwrap_err(),
+            TypeIdError::UnknownType{name: "my::Thing".to_owned()},
+        );
+
+        let mut data = Vec::new();
+        Envelope::<LengthPrefixString<UVarInt>, Verbose>::byte_serialize("x", &mut data).unwrap();
+        let decoder = DynamicDecoder::parse("twee::VerboseEnvelope<twee::LengthPrefixedString<twee::BE<u64>>>").unwrap();
+        match decoder.decode(&mut ByteCursor::new(&data)) {
+            Err(ParseOrIOError::Parse(DynamicError::SchemaMismatch(e))) => {
+                assert_eq!(e.found_type_id.as_deref(), Some("twee::LengthPrefixedString<twee::UVarInt>"));
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+}
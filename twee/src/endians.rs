@@ -1,12 +1,23 @@
+use crate::io::{ByteRead, ByteWrite};
+use crate::runtime::{DecodeWith, Encode};
 use crate::serde::*;
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct NumberEncodingSingleByte;
 
 impl ByteTypeId<u8> for NumberEncodingSingleByte {
     fn byte_type_id() -> Vec<&'static str> {
-        vec!["twee::SingleByteUnsigned"]
+        <Self as ByteConstTypeId<u8>>::TYPE_ID.pieces()
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::leaf(TypeKind::Number, "twee::SingleByteUnsigned").with_fixed_size(1)
+    }
+}
+
+impl ByteConstTypeId<u8> for NumberEncodingSingleByte {
+    const TYPE_ID: ConstTypeId = ConstTypeId::Str("twee::SingleByteUnsigned");
 }
 
 impl ByteConstSize<u8> for NumberEncodingSingleByte {
@@ -29,8 +40,16 @@ impl ByteConstSerialize<u8> for NumberEncodingSingleByte {
 
 impl ByteTypeId<i8> for NumberEncodingSingleByte {
     fn byte_type_id() -> Vec<&'static str> {
-        vec!["twee::SingleByteSigned"]
+        <Self as ByteConstTypeId<i8>>::TYPE_ID.pieces()
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::leaf(TypeKind::Number, "twee::SingleByteSigned").with_fixed_size(1)
+    }
+}
+
+impl ByteConstTypeId<i8> for NumberEncodingSingleByte {
+    const TYPE_ID: ConstTypeId = ConstTypeId::Str("twee::SingleByteSigned");
 }
 
 impl ByteConstSize<i8> for NumberEncodingSingleByte {
@@ -63,6 +82,14 @@ macro_rules! impl_single_byte_encoding {
                 fn byte_type_id() -> Vec<&'static str> {
                     <NumberEncodingSingleByte as ByteTypeId<$t>>::byte_type_id()
                 }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    <NumberEncodingSingleByte as ByteTypeId<$t>>::type_descriptor()
+                }
+            }
+
+            impl ByteConstTypeId<$t> for NumberEncodingLE {
+                const TYPE_ID: ConstTypeId = <NumberEncodingSingleByte as ByteConstTypeId<$t>>::TYPE_ID;
             }
 
             impl ByteConstSize<$t> for NumberEncodingLE {
@@ -88,6 +115,14 @@ macro_rules! impl_single_byte_encoding {
                 fn byte_type_id() -> Vec<&'static str> {
                     <NumberEncodingSingleByte as ByteTypeId<$t>>::byte_type_id()
                 }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    <NumberEncodingSingleByte as ByteTypeId<$t>>::type_descriptor()
+                }
+            }
+
+            impl ByteConstTypeId<$t> for NumberEncodingBE {
+                const TYPE_ID: ConstTypeId = <NumberEncodingSingleByte as ByteConstTypeId<$t>>::TYPE_ID;
             }
 
             impl ByteConstSize<$t> for NumberEncodingBE {
@@ -118,48 +153,66 @@ macro_rules! impl_encoding {
         $(
             impl ByteTypeId<$t> for NumberEncodingLE {
                 fn byte_type_id() -> Vec<&'static str> {
-                    vec![concat!("twee::LE<", stringify!($t), ">")]
+                    <Self as ByteConstTypeId<$t>>::TYPE_ID.pieces()
+                }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    TypeDescriptor::new(TypeKind::Number, "twee::LE", vec![TypeParam::Value(stringify!($t).into())])
+                        .with_fixed_size(::std::mem::size_of::<$t>() as u64)
                 }
             }
 
+            impl ByteConstTypeId<$t> for NumberEncodingLE {
+                const TYPE_ID: ConstTypeId = ConstTypeId::Str(concat!("twee::LE<", stringify!($t), ">"));
+            }
+
             impl ByteConstSize<$t> for NumberEncodingLE {
-                const BYTE_SIZE:usize = (<$t>::BITS/8) as usize;
+                const BYTE_SIZE:usize = ::std::mem::size_of::<$t>();
             }
 
             impl ByteConstDeserialize<$t> for NumberEncodingLE {
                 type ParseErr = !;
 
-                fn byte_const_deserialize(io: &[u8; (<$t>::BITS/8) as usize]) -> Result<$t, Self::ParseErr> {
+                fn byte_const_deserialize(io: &[u8; ::std::mem::size_of::<$t>()]) -> Result<$t, Self::ParseErr> {
                     Ok(<$t>::from_le_bytes(*io))
                 }
             }
 
             impl ByteConstSerialize<$t> for NumberEncodingLE {
-                fn byte_const_serialize(item: &$t, io: &mut [u8; (<$t>::BITS/8) as usize]) {
+                fn byte_const_serialize(item: &$t, io: &mut [u8; ::std::mem::size_of::<$t>()]) {
                     *io = item.to_le_bytes()
                 }
             }
 
             impl ByteTypeId<$t> for NumberEncodingBE {
                 fn byte_type_id() -> Vec<&'static str> {
-                    vec![concat!("twee::BE<", stringify!($t), ">")]
+                    <Self as ByteConstTypeId<$t>>::TYPE_ID.pieces()
+                }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    TypeDescriptor::new(TypeKind::Number, "twee::BE", vec![TypeParam::Value(stringify!($t).into())])
+                        .with_fixed_size(::std::mem::size_of::<$t>() as u64)
                 }
             }
 
+            impl ByteConstTypeId<$t> for NumberEncodingBE {
+                const TYPE_ID: ConstTypeId = ConstTypeId::Str(concat!("twee::BE<", stringify!($t), ">"));
+            }
+
             impl ByteConstSize<$t> for NumberEncodingBE {
-                const BYTE_SIZE:usize = (<$t>::BITS/8) as usize;
+                const BYTE_SIZE:usize = ::std::mem::size_of::<$t>();
             }
 
             impl ByteConstDeserialize<$t> for NumberEncodingBE {
                 type ParseErr = !;
 
-                fn byte_const_deserialize(io: &[u8; (<$t>::BITS/8) as usize]) -> Result<$t, Self::ParseErr> {
+                fn byte_const_deserialize(io: &[u8; ::std::mem::size_of::<$t>()]) -> Result<$t, Self::ParseErr> {
                     Ok(<$t>::from_be_bytes(*io))
                 }
             }
 
             impl ByteConstSerialize<$t> for NumberEncodingBE {
-                fn byte_const_serialize(item: &$t, io: &mut [u8; (<$t>::BITS/8) as usize]) {
+                fn byte_const_serialize(item: &$t, io: &mut [u8; ::std::mem::size_of::<$t>()]) {
                     *io = item.to_be_bytes()
                 }
             }
@@ -170,6 +223,66 @@ macro_rules! impl_encoding {
 impl_encoding! {
     u16, u32, u64, u128,
     i16, i32, i64, i128,
+    f32, f64,
+}
+
+/// A byte order picked at runtime, for formats that declare it in a header. It encodes every type [`NumberEncodingLE`] and [`NumberEncodingBE`] do, through [`Encode`] and [`DecodeWith`].
+///
+/// Use the `little`/`big` form of [`magic_bytes_type!`][crate::magic_bytes::magic_bytes_type] to read it from magic bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub const fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            Self::Little
+        } else {
+            Self::Big
+        }
+    }
+}
+
+macro_rules! impl_runtime_encoding {
+    ($($t:ty,)*) => {
+        $(
+            impl Encode<$t> for Endianness {
+                fn encode<W: ByteWrite>(&self, item: &$t, io: &mut W) -> Result<(), W::Err> {
+                    match self {
+                        Self::Little => NumberEncodingLE::byte_serialize(item, io),
+                        Self::Big => NumberEncodingBE::byte_serialize(item, io),
+                    }
+                }
+
+                fn encoded_size(&self, _item: &$t) -> u64 {
+                    ::std::mem::size_of::<$t>() as u64
+                }
+            }
+
+            impl<C: ?Sized> DecodeWith<$t, C> for Endianness {
+                type ParseErr = !;
+
+                fn decode_with<R: ByteRead>(&self, io: &mut R, _ctx: &mut C) -> Result<$t, ParseOrIOError<Self::ParseErr, R::Err>> {
+                    match self {
+                        Self::Little => NumberEncodingLE::byte_deserialize(io),
+                        Self::Big => NumberEncodingBE::byte_deserialize(io),
+                    }
+                }
+
+                fn skip_with<R: ByteRead>(&self, io: &mut R, _ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+                    io.skip_buf(::std::mem::size_of::<$t>() as u64).map_err(ParseOrIOError::IO)
+                }
+            }
+        )*
+    };
+}
+
+impl_runtime_encoding! {
+    u8, u16, u32, u64, u128,
+    i8, i16, i32, i64, i128,
+    f32, f64,
 }
 
 #[cfg(test)]
@@ -237,6 +350,48 @@ mod tests {
             u8, u16, u32, u64, u128,
             i8, i16, i32, i64, i128,
         );
+        for v in [f64::MIN, 0.0, 1.0, f64::MAX, f64::INFINITY] {
+            assert_serde::<NumberEncodingLE, f64>(&v);
+            assert_serde::<NumberEncodingBE, f64>(&v);
+            assert_serde::<NumberEncodingLE, f32>(&(v as f32));
+            assert_serde::<NumberEncodingBE, f32>(&(v as f32));
+        }
+    }
+
+    #[test]
+    fn runtime_endianness() {
+        use crate::io::ByteCursor;
+        use crate::runtime::Decode;
+        use crate::magic_bytes::magic_bytes_type;
+
+        magic_bytes_type!{
+            struct TiffOrder {
+                little: [b'I', b'I'],
+                big: [b'M', b'M'],
+            }
+        }
+        crate::assert_serde::<TiffOrder, _>(&Endianness::Little);
+        crate::assert_serde::<TiffOrder, _>(&Endianness::Big);
+        let res:Result<Endianness, _> = TiffOrder::byte_deserialize(&mut ByteCursor::new(b"IM"));
+        assert_eq!(res, Err(ParseOrIOError::Parse(crate::magic_bytes::EndianMagicMismatch{little: *b"II", big: *b"MM", found: *b"IM"})));
+
+        for order in [Endianness::Little, Endianness::Big] {
+            let mut data = Vec::new();
+            TiffOrder::byte_serialize(&order, &mut data).unwrap();
+            order.encode(&42u16, &mut data).unwrap();
+            order.encode(&1.5f64, &mut data).unwrap();
+
+            let mut cur = ByteCursor::new(&data);
+            let found:Endianness = TiffOrder::byte_deserialize(&mut cur).unwrap();
+            assert_eq!(found, order);
+            assert_eq!(found.decode(&mut cur), Ok(42u16));
+            assert_eq!(found.decode(&mut cur), Ok(1.5f64));
+            assert!(cur.at_end());
+        }
+        let mut data = Vec::new();
+        Endianness::Big.encode(&1u16, &mut data).unwrap();
+        Endianness::Little.encode(&1u16, &mut data).unwrap();
+        assert_eq!(data, [0, 1, 1, 0]);
     }
 
     #[test]
@@ -0,0 +1,455 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+pub use crate::descriptor::{fnv1a64, type_fingerprint, FNV_OFFSET_BASIS, FNV_PRIME};
+use crate::endians::NumberEncodingLE;
+use crate::io::{ByteRead, ByteWrite};
+use crate::serde::{ByteConstTypeId, ByteDeserialize, ByteSerialize, ByteTypeId, ParseOrIOError};
+use crate::string::{LengthPrefixString, StringParseError};
+use crate::varint::{UVarInt, VarIntTooBig};
+
+/// The envelope holds just the fingerprint.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Compact;
+
+/// The envelope holds the fingerprint followed by the full type id, so a mismatch can say what the data actually was.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Verbose;
+
+pub trait EnvelopeHeader: Default + Copy {
+    const HAS_TYPE_ID: bool;
+    const TYPE_NAME: &'static str;
+}
+
+impl EnvelopeHeader for Compact {
+    const HAS_TYPE_ID: bool = false;
+    const TYPE_NAME: &'static str = "twee::Envelope<";
+}
+
+impl EnvelopeHeader for Verbose {
+    const HAS_TYPE_ID: bool = true;
+    const TYPE_NAME: &'static str = "twee::VerboseEnvelope<";
+}
+
+type TypeIdEncoder = LengthPrefixString<UVarInt>;
+
+/// Where an [`Envelope`] gets the type id of `E` that it writes and checks
+pub trait TypeIdSource<E, T: ?Sized>: Default + Copy {
+    fn fingerprint() -> u64;
+
+    fn type_id() -> String;
+
+    /// Writes the type id the way [`LengthPrefixString<UVarInt>`][LengthPrefixString] would
+    fn write_type_id<W: ByteWrite>(io: &mut W) -> Result<(), W::Err>;
+
+    fn type_id_size() -> u64;
+}
+
+/// Takes the type id from [`ByteConstTypeId`], so headers are written without allocating.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ConstTypeIds;
+
+/// Builds the type id with [`ByteTypeId::byte_type_id`] for every header, for encoders that don't implement [`ByteConstTypeId`]. The bytes are the same as with [`ConstTypeIds`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RuntimeTypeIds;
+
+impl<E: ByteConstTypeId<T>, T: ?Sized> TypeIdSource<E, T> for ConstTypeIds {
+    fn fingerprint() -> u64 {
+        E::TYPE_FINGERPRINT
+    }
+
+    fn type_id() -> String {
+        E::TYPE_ID.to_string()
+    }
+
+    fn write_type_id<W: ByteWrite>(io: &mut W) -> Result<(), W::Err> {
+        UVarInt::byte_serialize(&(E::TYPE_ID.byte_len() as u64), io)?;
+        E::TYPE_ID.write_to(io)
+    }
+
+    fn type_id_size() -> u64 {
+        let len = E::TYPE_ID.byte_len() as u64;
+        UVarInt::size(&len) + len
+    }
+}
+
+impl<E: ByteTypeId<T>, T: ?Sized> TypeIdSource<E, T> for RuntimeTypeIds {
+    fn fingerprint() -> u64 {
+        type_fingerprint::<E, T>()
+    }
+
+    fn type_id() -> String {
+        E::byte_type_id().concat()
+    }
+
+    fn write_type_id<W: ByteWrite>(io: &mut W) -> Result<(), W::Err> {
+        TypeIdEncoder::byte_serialize(&<Self as TypeIdSource<E, T>>::type_id(), io)
+    }
+
+    fn type_id_size() -> u64 {
+        <TypeIdEncoder as ByteSerialize<String>>::size(&<Self as TypeIdSource<E, T>>::type_id())
+    }
+}
+
+/// Encodes a value with `E`, preceded by [`ByteConstTypeId::TYPE_FINGERPRINT`] of `E` as a little-endian `u64`, so that data written by a different encoder is rejected instead of misread.
+///
+/// Implemented for lists and strings; use [`impl_envelope!`] for other value types. Encoders that only implement [`ByteTypeId`] can be used with [`RuntimeTypeIds`] for `S`.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Envelope<E, H = Compact, S = ConstTypeIds> {
+    encoder: PhantomData<E>,
+    header: PhantomData<H>,
+    type_ids: PhantomData<S>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SchemaMismatch {
+    pub expected: u64,
+    pub found: u64,
+    pub expected_type_id: String,
+    /// Only known for [`Verbose`] envelopes
+    pub found_type_id: Option<String>,
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "schema mismatch: expected {} ({:016x}), found ", self.expected_type_id, self.expected)?;
+        match &self.found_type_id {
+            Some(type_id) => write!(f, "{} ({:016x})", type_id, self.found),
+            None => write!(f, "{:016x}", self.found),
+        }
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvelopeError<P> {
+    SchemaMismatch(SchemaMismatch),
+    TypeIdParseError(StringParseError<VarIntTooBig>),
+    PayloadParseError(P),
+}
+
+impl<P: fmt::Display> fmt::Display for EnvelopeError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SchemaMismatch(e) => e.fmt(f),
+            Self::TypeIdParseError(e) => write!(f, "invalid envelope type id: {}", e),
+            Self::PayloadParseError(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<P: std::error::Error + 'static> std::error::Error for EnvelopeError<P> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::SchemaMismatch(e) => Some(e),
+            Self::TypeIdParseError(e) => Some(e),
+            Self::PayloadParseError(e) => Some(e),
+        }
+    }
+}
+
+impl<E, H: EnvelopeHeader, S> Envelope<E, H, S> {
+    pub fn envelope_type_id<T: ?Sized>() -> Vec<&'static str>
+    where
+        E: ByteTypeId<T>,
+    {
+        let mut res = Vec::new();
+        res.push(H::TYPE_NAME);
+        res.extend_from_slice(E::byte_type_id().as_slice());
+        res.push(">");
+        res
+    }
+
+    pub fn envelope_type_descriptor<T: ?Sized>() -> TypeDescriptor
+    where
+        E: ByteTypeId<T>,
+    {
+        TypeDescriptor::new(TypeKind::Wrapper, H::TYPE_NAME.trim_end_matches('<'), vec![TypeParam::Type(E::type_descriptor())])
+    }
+
+    pub fn write_header<T: ?Sized, W: ByteWrite>(io: &mut W) -> Result<(), W::Err>
+    where
+        S: TypeIdSource<E, T>,
+    {
+        NumberEncodingLE::byte_serialize(&S::fingerprint(), io)?;
+        if H::HAS_TYPE_ID {
+            S::write_type_id(io)?;
+        }
+        Ok(())
+    }
+
+    pub fn header_size<T: ?Sized>() -> u64
+    where
+        S: TypeIdSource<E, T>,
+    {
+        let mut size = 8;
+        if H::HAS_TYPE_ID {
+            size += S::type_id_size();
+        }
+        size
+    }
+
+    /// Reads the header and checks it matches `E`, leaving the reader at the payload
+    pub fn read_header<T: ?Sized, P, R: ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<EnvelopeError<P>, R::Err>>
+    where
+        S: TypeIdSource<E, T>,
+    {
+        let found:u64 = NumberEncodingLE::byte_deserialize(io).map_err(|e| e.map_parse(|n| match n {}))?;
+        let expected = S::fingerprint();
+        let found_type_id = match (H::HAS_TYPE_ID, found == expected) {
+            (false, _) => None,
+            (true, true) => {
+                <TypeIdEncoder as ByteDeserialize<String>>::byte_skip(io).map_err(|e| e.map_parse(EnvelopeError::TypeIdParseError))?;
+                None
+            },
+            (true, false) => Some(TypeIdEncoder::byte_deserialize(io).map_err(|e| e.map_parse(EnvelopeError::TypeIdParseError))?),
+        };
+        if found != expected {
+            return Err(ParseOrIOError::Parse(EnvelopeError::SchemaMismatch(SchemaMismatch{
+                expected,
+                found,
+                expected_type_id: S::type_id(),
+                found_type_id,
+            })));
+        }
+        Ok(())
+    }
+}
+
+/// Implements the serde traits of [`Envelope`] for the given value types, each preceded by the generic parameters it needs in brackets.
+///
+/// They can't be blanket impls over every type without overlapping the ones for `ByteConst*` encoders, so they're spelled out per value type like the other combinators. Downstream crates can use this for their own types.
+#[macro_export]
+macro_rules! impl_envelope {
+    ($([$($g:ident),*] $t:ty;)*) => {
+        $(
+            impl<E, H, S, $($g),*> $crate::serde::ByteTypeId<$t> for $crate::envelope::Envelope<E, H, S>
+            where
+                E: $crate::serde::ByteTypeId<$t>,
+                H: $crate::envelope::EnvelopeHeader,
+                S: ::std::default::Default + ::std::marker::Copy,
+            {
+                fn byte_type_id() -> ::std::vec::Vec<&'static str> {
+                    Self::envelope_type_id::<$t>()
+                }
+
+                fn type_descriptor() -> $crate::descriptor::TypeDescriptor {
+                    Self::envelope_type_descriptor::<$t>()
+                }
+            }
+
+            impl<E, H, S, $($g),*> $crate::serde::ByteConstTypeId<$t> for $crate::envelope::Envelope<E, H, S>
+            where
+                E: $crate::serde::ByteConstTypeId<$t>,
+                H: $crate::envelope::EnvelopeHeader,
+                S: ::std::default::Default + ::std::marker::Copy,
+            {
+                const TYPE_ID: $crate::descriptor::ConstTypeId = $crate::descriptor::ConstTypeId::Seq(&[
+                    $crate::descriptor::ConstTypeId::Str(H::TYPE_NAME),
+                    E::TYPE_ID,
+                    $crate::descriptor::ConstTypeId::Str(">"),
+                ]);
+            }
+
+            impl<E, H, S, $($g),*> $crate::serde::ByteSerialize<$t> for $crate::envelope::Envelope<E, H, S>
+            where
+                E: $crate::serde::ByteSerialize<$t>,
+                H: $crate::envelope::EnvelopeHeader,
+                S: $crate::envelope::TypeIdSource<E, $t>,
+            {
+                fn byte_serialize<W: $crate::io::ByteWrite>(item: &$t, io: &mut W) -> ::std::result::Result<(), W::Err> {
+                    Self::write_header::<$t, W>(io)?;
+                    E::byte_serialize(item, io)
+                }
+
+                fn byte_serialize_seekable<W: $crate::io::SeekableByteWrite>(item: &$t, io: &mut W) -> ::std::result::Result<(), W::Err> {
+                    Self::write_header::<$t, W>(io)?;
+                    E::byte_serialize_seekable(item, io)
+                }
+
+                fn size(item: &$t) -> u64 {
+                    Self::header_size::<$t>() + E::size(item)
+                }
+            }
+
+            impl<E, H, S, $($g),*> $crate::serde::ByteDeserialize<$t> for $crate::envelope::Envelope<E, H, S>
+            where
+                E: $crate::serde::ByteDeserialize<$t>,
+                H: $crate::envelope::EnvelopeHeader,
+                S: $crate::envelope::TypeIdSource<E, $t>,
+            {
+                type ParseErr = $crate::envelope::EnvelopeError<E::ParseErr>;
+
+                fn byte_deserialize<R: $crate::io::ByteRead>(io: &mut R) -> ::std::result::Result<$t, $crate::serde::ParseOrIOError<Self::ParseErr, R::Err>> {
+                    Self::read_header::<$t, _, R>(io)?;
+                    E::byte_deserialize(io).map_err(|e| e.map_parse($crate::envelope::EnvelopeError::PayloadParseError))
+                }
+
+                fn byte_deserialize_in_place<R: $crate::io::ByteRead>(io: &mut R, place: &mut $t) -> ::std::result::Result<(), $crate::serde::ParseOrIOError<Self::ParseErr, R::Err>> {
+                    Self::read_header::<$t, _, R>(io)?;
+                    E::byte_deserialize_in_place(io, place).map_err(|e| e.map_parse($crate::envelope::EnvelopeError::PayloadParseError))
+                }
+
+                fn byte_skip<R: $crate::io::ByteRead>(io: &mut R) -> ::std::result::Result<(), $crate::serde::ParseOrIOError<Self::ParseErr, R::Err>> {
+                    Self::read_header::<$t, _, R>(io)?;
+                    E::byte_skip(io).map_err(|e| e.map_parse($crate::envelope::EnvelopeError::PayloadParseError))
+                }
+
+                fn byte_validate<R: $crate::io::ByteRead>(io: &mut R) -> ::std::result::Result<(), $crate::serde::ParseOrIOError<Self::ParseErr, R::Err>> {
+                    Self::read_header::<$t, _, R>(io)?;
+                    E::byte_validate(io).map_err(|e| e.map_parse($crate::envelope::EnvelopeError::PayloadParseError))
+                }
+            }
+        )*
+    };
+}
+
+impl_envelope!{
+    [V] Vec<V>;
+    [] String;
+}
+
+// `[V]` and `str` can only be serialized
+macro_rules! impl_envelope_unsized {
+    ($([$($g:ident),*] $t:ty;)*) => {
+        $(
+            impl<E, H, S, $($g),*> ByteTypeId<$t> for Envelope<E, H, S>
+            where
+                E: ByteTypeId<$t>,
+                H: EnvelopeHeader,
+                S: Default + Copy,
+            {
+                fn byte_type_id() -> Vec<&'static str> {
+                    Self::envelope_type_id::<$t>()
+                }
+
+                fn type_descriptor() -> TypeDescriptor {
+                    Self::envelope_type_descriptor::<$t>()
+                }
+            }
+
+            impl<E, H, S, $($g),*> ByteConstTypeId<$t> for Envelope<E, H, S>
+            where
+                E: ByteConstTypeId<$t>,
+                H: EnvelopeHeader,
+                S: Default + Copy,
+            {
+                const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+                    ConstTypeId::Str(H::TYPE_NAME),
+                    E::TYPE_ID,
+                    ConstTypeId::Str(">"),
+                ]);
+            }
+
+            impl<E, H, S, $($g),*> ByteSerialize<$t> for Envelope<E, H, S>
+            where
+                E: ByteSerialize<$t>,
+                H: EnvelopeHeader,
+                S: TypeIdSource<E, $t>,
+            {
+                fn byte_serialize<W: ByteWrite>(item: &$t, io: &mut W) -> Result<(), W::Err> {
+                    Self::write_header::<$t, W>(io)?;
+                    E::byte_serialize(item, io)
+                }
+
+                fn byte_serialize_seekable<W: crate::io::SeekableByteWrite>(item: &$t, io: &mut W) -> Result<(), W::Err> {
+                    Self::write_header::<$t, W>(io)?;
+                    E::byte_serialize_seekable(item, io)
+                }
+
+                fn size(item: &$t) -> u64 {
+                    Self::header_size::<$t>() + E::size(item)
+                }
+            }
+        )*
+    };
+}
+
+impl_envelope_unsized!{
+    [V] [V];
+    [] str;
+}
+
+pub use impl_envelope;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::ByteCursor;
+    use crate::lists::LengthPrefixList;
+    use crate::endians::NumberEncodingBE as BE;
+
+    type Nums = LengthPrefixList<UVarInt, NumberEncodingLE>;
+    type NumsBE = LengthPrefixList<UVarInt, BE>;
+
+    #[test]
+    fn blarg() {
+        let nums = vec![1u32, 2, 3];
+        crate::assert_serde::<Envelope<Nums>, _>(&nums);
+        crate::assert_serde::<Envelope<Nums, Verbose>, _>(&nums);
+        assert_eq!(fnv1a64(FNV_OFFSET_BASIS, b"a"), 0xaf63dc4c8601ec8c);
+
+        let mut data = Vec::new();
+        Envelope::<Nums>::byte_serialize(&nums, &mut data).unwrap();
+        assert_eq!(data[..8], type_fingerprint::<Nums, Vec<u32>>().to_le_bytes());
+        let res:Result<Vec<u32>, _> = Envelope::<NumsBE>::byte_deserialize(&mut ByteCursor::new(&data));
+        assert_eq!(res, Err(ParseOrIOError::Parse(EnvelopeError::SchemaMismatch(SchemaMismatch{
+            expected: type_fingerprint::<NumsBE, Vec<u32>>(),
+            found: type_fingerprint::<Nums, Vec<u32>>(),
+            expected_type_id: "twee::LengthPrefixedList<twee::UVarInt,twee::BE<u32>>".to_string(),
+            found_type_id: None,
+        }))));
+
+        let mut data = Vec::new();
+        Envelope::<Nums, Verbose>::byte_serialize(&nums, &mut data).unwrap();
+        let res:Result<Vec<u32>, _> = Envelope::<NumsBE, Verbose>::byte_deserialize(&mut ByteCursor::new(&data));
+        let Err(ParseOrIOError::Parse(EnvelopeError::SchemaMismatch(e))) = res else { panic!() };
+        assert_eq!(e.found_type_id.as_deref(), Some("twee::LengthPrefixedList<twee::UVarInt,twee::LE<u32>>"));
+    }
+
+    #[test]
+    fn runtime_type_ids() {
+        type Str = LengthPrefixString<UVarInt>;
+
+        // no ByteConstTypeId
+        #[derive(Debug, Default, Copy, Clone)]
+        struct Plain;
+
+        impl ByteTypeId<String> for Plain {
+            fn byte_type_id() -> Vec<&'static str> {
+                vec!["test::", "Plain"]
+            }
+        }
+
+        impl ByteSerialize<String> for Plain {
+            fn byte_serialize<W: ByteWrite>(item: &String, io: &mut W) -> Result<(), W::Err> {
+                Str::byte_serialize(item, io)
+            }
+        }
+
+        impl ByteDeserialize<String> for Plain {
+            type ParseErr = StringParseError<VarIntTooBig>;
+
+            fn byte_deserialize<R: ByteRead>(io: &mut R) -> Result<String, ParseOrIOError<Self::ParseErr, R::Err>> {
+                Str::byte_deserialize(io)
+            }
+        }
+
+        let s = "hello".to_string();
+        crate::assert_serde::<Envelope<Plain, Verbose, RuntimeTypeIds>, _>(&s);
+
+        let mut data = Vec::new();
+        Envelope::<Str, Verbose>::byte_serialize(&s, &mut data).unwrap();
+        let mut runtime = Vec::new();
+        Envelope::<Str, Verbose, RuntimeTypeIds>::byte_serialize(&s, &mut runtime).unwrap();
+        assert_eq!(data, runtime);
+        assert_eq!(Envelope::<Str, Verbose>::size(&s), data.len() as u64);
+
+        let res:Result<String, _> = Envelope::<Plain, Verbose, RuntimeTypeIds>::byte_deserialize(&mut ByteCursor::new(&data));
+        let Err(ParseOrIOError::Parse(EnvelopeError::SchemaMismatch(e))) = res else { panic!() };
+        assert_eq!(e.expected_type_id, "test::Plain");
+        assert_eq!(e.found_type_id.as_deref(), Some("twee::LengthPrefixedString<twee::UVarInt>"));
+    }
+}
@@ -0,0 +1,243 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io as stdio;
+
+use crate::io::{ByteCursor, EndOfBufferError, OutOfSpaceError};
+use crate::serde::{ByteDeserialize, ParseOrIOError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The bytes were read successfully but don't form a valid value
+    Parse,
+    /// The input ended before a complete value could be read
+    UnexpectedEof,
+    /// The output buffer was too small
+    OutOfSpace,
+    /// The underlying reader or writer failed
+    Io,
+}
+
+impl ErrorKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Parse => "parse error",
+            Self::UnexpectedEof => "unexpected end of input",
+            Self::OutOfSpace => "out of space",
+            Self::Io => "io error",
+        }
+    }
+
+    /// Guesses the kind of an error that came out of a [`ByteRead`][`crate::io::ByteRead`] or [`ByteWrite`][`crate::io::ByteWrite`]
+    fn of_io_error(e: &(dyn StdError + 'static)) -> Self {
+        if e.is::<EndOfBufferError>() {
+            return Self::UnexpectedEof;
+        }
+        if e.is::<OutOfSpaceError>() {
+            return Self::OutOfSpace;
+        }
+        match e.downcast_ref::<stdio::Error>() {
+            Some(io_err) if io_err.kind() == stdio::ErrorKind::UnexpectedEof => Self::UnexpectedEof,
+            _ => Self::Io,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A type-erased error for when the exact parse and io error types don't matter.
+///
+/// Any [`ParseOrIOError`] whose parameters implement [`std::error::Error`] converts into this with `?`, and this in turn converts into [`std::io::Error`].
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    source: Box<dyn StdError + Send + Sync + 'static>,
+}
+
+impl Error {
+    pub fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Into<Box<dyn StdError + Send + Sync + 'static>>,
+    {
+        Self{
+            kind,
+            offset: None,
+            source: source.into(),
+        }
+    }
+
+    /// Records the byte offset in the input at which the error happened
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    pub fn get_ref(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        &*self.source
+    }
+
+    pub fn into_inner(self) -> Box<dyn StdError + Send + Sync + 'static> {
+        self.source
+    }
+}
+
+impl ByteCursor<'_> {
+    /// Decodes a `T` with `E`, recording where the cursor was when decoding failed as the error's offset
+    pub fn deserialize<E, T>(&mut self) -> Result<T, Error>
+    where
+        E: ByteDeserialize<T>,
+        E::ParseErr: StdError + Send + Sync + 'static,
+    {
+        E::byte_deserialize(self).map_err(|e| Error::from(e).with_offset(self.position() as u64))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+impl<P, I> From<ParseOrIOError<P, I>> for Error
+where
+    P: StdError + Send + Sync + 'static,
+    I: StdError + Send + Sync + 'static,
+{
+    fn from(e: ParseOrIOError<P, I>) -> Self {
+        match e {
+            ParseOrIOError::Parse(p) => Self::new(ErrorKind::Parse, p),
+            ParseOrIOError::IO(i) => Self::new(ErrorKind::of_io_error(&i), i),
+        }
+    }
+}
+
+impl From<EndOfBufferError> for Error {
+    fn from(e: EndOfBufferError) -> Self {
+        Self::new(ErrorKind::UnexpectedEof, e)
+    }
+}
+
+impl From<OutOfSpaceError> for Error {
+    fn from(e: OutOfSpaceError) -> Self {
+        Self::new(ErrorKind::OutOfSpace, e)
+    }
+}
+
+impl From<stdio::Error> for Error {
+    fn from(e: stdio::Error) -> Self {
+        Self::new(ErrorKind::of_io_error(&e), e)
+    }
+}
+
+impl From<Error> for stdio::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e.kind {
+            ErrorKind::Parse => stdio::ErrorKind::InvalidData,
+            ErrorKind::UnexpectedEof => stdio::ErrorKind::UnexpectedEof,
+            ErrorKind::OutOfSpace => stdio::ErrorKind::WriteZero,
+            ErrorKind::Io => stdio::ErrorKind::Other,
+        };
+        if e.offset.is_none() && e.source.is::<stdio::Error>() {
+            return *e.source.downcast::<stdio::Error>().unwrap();
+        }
+        stdio::Error::new(kind, e)
+    }
+}
+
+impl<P, I> From<ParseOrIOError<P, I>> for stdio::Error
+where
+    P: StdError + Send + Sync + 'static,
+    I: StdError + Send + Sync + 'static,
+{
+    fn from(e: ParseOrIOError<P, I>) -> Self {
+        Error::from(e).into()
+    }
+}
+
+impl From<EndOfBufferError> for stdio::Error {
+    fn from(e: EndOfBufferError) -> Self {
+        stdio::Error::new(stdio::ErrorKind::UnexpectedEof, e)
+    }
+}
+
+impl From<OutOfSpaceError> for stdio::Error {
+    fn from(e: OutOfSpaceError) -> Self {
+        stdio::Error::new(stdio::ErrorKind::WriteZero, e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::ByteCursor;
+    use crate::serde::ByteDeserialize;
+    use crate::endians::NumberEncodingBE as BE;
+    use crate::varint::{UVarInt, VarIntTooBig};
+
+    fn read_u32(buf: &[u8]) -> stdio::Result<u32> {
+        let mut cur = ByteCursor::new(buf);
+        Ok(BE::byte_deserialize(&mut cur)?)
+    }
+
+    fn read_uvarint(buf: &[u8]) -> Result<u64, Box<dyn StdError>> {
+        let mut cur = ByteCursor::new(buf);
+        Ok(UVarInt::byte_deserialize(&mut cur).map_err(Error::from)?)
+    }
+
+    #[test]
+    fn blarg() {
+        assert_eq!(read_u32(&[0, 0, 1, 0]).unwrap(), 256);
+        assert_eq!(read_u32(&[0, 0, 1]).unwrap_err().kind(), stdio::ErrorKind::UnexpectedEof);
+        assert_eq!(read_uvarint(&[0x81, 0x01]).unwrap(), 129);
+
+        let too_big = [0xff; 10];
+        let e = read_uvarint(&too_big).unwrap_err();
+        let e = e.downcast_ref::<Error>().unwrap();
+        assert_eq!(e.kind(), ErrorKind::Parse);
+        assert!(e.get_ref().is::<VarIntTooBig>());
+
+        let io_err:stdio::Error = UVarInt::byte_deserialize(&mut ByteCursor::new(&too_big)).unwrap_err().into();
+        assert_eq!(io_err.kind(), stdio::ErrorKind::InvalidData);
+
+        let mut cur = ByteCursor::new(&[0, 0, 0, 1, 0, 0]);
+        assert_eq!(cur.deserialize::<BE, u32>().unwrap(), 1);
+        let e = cur.deserialize::<BE, u32>().unwrap_err();
+        assert_eq!((e.kind(), e.offset()), (ErrorKind::UnexpectedEof, Some(4)));
+    }
+
+    #[test]
+    fn io_passthrough() {
+        let orig = stdio::Error::new(stdio::ErrorKind::BrokenPipe, "oops");
+        let e = Error::from(ParseOrIOError::<VarIntTooBig, _>::IO(orig));
+        assert_eq!(e.kind(), ErrorKind::Io);
+        let back:stdio::Error = e.into();
+        assert_eq!(back.kind(), stdio::ErrorKind::BrokenPipe);
+
+        let e = Error::from(EndOfBufferError).with_offset(12);
+        assert_eq!(e.offset(), Some(12));
+        assert_eq!(e.to_string(), "unexpected end of input at offset 12: attempted to read past end of buffer");
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::io::{ByteRead, ByteWrite, ByteCursor, Chain, ChainError};
+use crate::serde::{ByteDeserialize, ByteSerialize, ParseOrIOError};
+
+/// Writes a sequence of messages, each encoded with `E` and preceded by its length in bytes encoded with `LE`.
+#[derive(Debug, Clone)]
+pub struct FrameWriter<LE, E, W> {
+    inner: W,
+    length_encoder: PhantomData<LE>,
+    encoder: PhantomData<E>,
+}
+
+impl<LE, E, W> FrameWriter<LE, E, W> {
+    pub fn new(inner: W) -> Self {
+        Self{
+            inner,
+            length_encoder: PhantomData,
+            encoder: PhantomData,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<LE, E, W> FrameWriter<LE, E, W>
+where
+    LE: ByteSerialize<u64>,
+    W: ByteWrite,
+{
+    pub fn write<T: ?Sized>(&mut self, item: &T) -> Result<(), W::Err>
+    where
+        E: ByteSerialize<T>,
+    {
+        let len = E::size(item);
+        LE::byte_serialize(&len, &mut self.inner)?;
+        E::byte_serialize(item, &mut self.inner)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameError<L, P, I> {
+    /// The length prefix couldn't be parsed. The rest of the input can't be read.
+    Length(L),
+    /// The frame was read, but its contents couldn't be parsed. Reading can continue with the next frame.
+    Payload(P),
+    /// The frame was read and parsed, but not all of it was used. Reading can continue with the next frame.
+    TrailingBytes{unused: u64},
+    /// The frame was read, but its contents run past its end. Reading can continue with the next frame.
+    Overrun,
+    /// The input ended partway through a frame.
+    Truncated{expected: u64, found: u64},
+    /// The length prefix is larger than the configured maximum. The rest of the input can't be read.
+    TooLarge{len: u64, max: u64},
+    IO(I),
+}
+
+impl<L, P, I> fmt::Display for FrameError<L, P, I>
+where
+    L: fmt::Display,
+    P: fmt::Display,
+    I: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length(e) => write!(f, "invalid frame length: {}", e),
+            Self::Payload(e) => write!(f, "invalid frame: {}", e),
+            Self::TrailingBytes{unused} => write!(f, "{} unused bytes at end of frame", unused),
+            Self::Overrun => write!(f, "frame contents run past the end of the frame"),
+            Self::Truncated{expected, found} => write!(f, "frame truncated after {} of {} bytes", found, expected),
+            Self::TooLarge{len, max} => write!(f, "frame of {} bytes exceeds maximum of {}", len, max),
+            Self::IO(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl<L, P, I> std::error::Error for FrameError<L, P, I>
+where
+    L: std::error::Error + 'static,
+    P: std::error::Error + 'static,
+    I: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Length(e) => Some(e),
+            Self::Payload(e) => Some(e),
+            Self::IO(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Frames longer than this are refused unless [`FrameReader::with_max_frame_size`] says otherwise
+pub const DEFAULT_MAX_FRAME_SIZE:u64 = 16 << 20;
+
+/// Reads a sequence of messages written by [`FrameWriter`].
+///
+/// Iteration ends with `None` when the input ends cleanly between two frames, which is found out with [`ByteRead::read_up_to`]. A frame whose contents fail to parse is skipped over after yielding its error; any other error ends the iteration.
+#[derive(Debug, Clone)]
+pub struct FrameReader<LE, E, T, R> {
+    inner: R,
+    max_frame_size: u64,
+    done: bool,
+    length_encoder: PhantomData<LE>,
+    encoder: PhantomData<E>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<LE, E, T, R> FrameReader<LE, E, T, R> {
+    pub fn new(inner: R) -> Self {
+        Self{
+            inner,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            done: false,
+            length_encoder: PhantomData,
+            encoder: PhantomData,
+            value: PhantomData,
+        }
+    }
+
+    /// Refuses frames longer than `max` bytes, rather than trying to read them in. Defaults to [`DEFAULT_MAX_FRAME_SIZE`].
+    pub fn with_max_frame_size(mut self, max: u64) -> Self {
+        self.max_frame_size = max;
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<LE, E, T, R> FrameReader<LE, E, T, R>
+where
+    LE: ByteDeserialize<u64>,
+    E: ByteDeserialize<T>,
+    R: ByteRead,
+{
+    fn read_frame(&mut self) -> Option<<Self as Iterator>::Item> {
+        let first = match self.inner.read_up_to(1) {
+            Ok(buf) if buf.is_empty() => return None,
+            Ok(buf) => buf[0],
+            Err(e) => return Some(Err(FrameError::IO(e))),
+        };
+        let first = [first];
+        let mut prefixed = Chain::new(ByteCursor::new(&first), &mut self.inner);
+        let len = match LE::byte_deserialize(&mut prefixed) {
+            Ok(len) => len,
+            Err(ParseOrIOError::Parse(e)) => return Some(Err(FrameError::Length(e))),
+            Err(ParseOrIOError::IO(ChainError::Second(e))) => return Some(Err(FrameError::IO(e))),
+            Err(ParseOrIOError::IO(ChainError::First(_))) => unreachable!("a ByteCursor never fails to read_up_to"),
+        };
+        if len > self.max_frame_size {
+            return Some(Err(FrameError::TooLarge{len, max: self.max_frame_size}));
+        }
+        let buf = match self.inner.read_up_to(len) {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(FrameError::IO(e))),
+        };
+        let found = buf.len() as u64;
+        if found < len {
+            return Some(Err(FrameError::Truncated{expected: len, found}));
+        }
+        let mut cur = ByteCursor::new(&buf);
+        let res = match E::byte_deserialize(&mut cur) {
+            Ok(v) if cur.at_end() => Ok(v),
+            Ok(_) => Err(FrameError::TrailingBytes{unused: cur.remaining() as u64}),
+            Err(ParseOrIOError::Parse(e)) => Err(FrameError::Payload(e)),
+            Err(ParseOrIOError::IO(_)) => Err(FrameError::Overrun),
+        };
+        Some(res)
+    }
+}
+
+impl<LE, E, T, R> Iterator for FrameReader<LE, E, T, R>
+where
+    LE: ByteDeserialize<u64>,
+    E: ByteDeserialize<T>,
+    R: ByteRead,
+{
+    type Item = Result<T, FrameError<LE::ParseErr, E::ParseErr, R::Err>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.read_frame();
+        if !matches!(res, Some(Ok(_)) | Some(Err(FrameError::Payload(_) | FrameError::TrailingBytes{..} | FrameError::Overrun))) {
+            self.done = true;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::varint::UVarInt;
+    use crate::endians::NumberEncodingBE as BE;
+    use crate::string::{LengthPrefixString, StringParseError};
+
+    type Str = LengthPrefixString<UVarInt>;
+
+    fn write_frames<LE: ByteSerialize<u64>>(strings: &[&str]) -> Vec<u8> {
+        let mut w = FrameWriter::<LE, Str, _>::new(Vec::new());
+        for s in strings {
+            w.write(*s).unwrap();
+        }
+        w.into_inner()
+    }
+
+    #[test]
+    fn blarg() {
+        let long = "x".repeat(200);
+        let strings = ["hello", "", long.as_str()];
+        let data = write_frames::<UVarInt>(&strings);
+        let r = FrameReader::<UVarInt, Str, String, _>::new(ByteCursor::new(&data));
+        let read:Vec<String> = r.map(Result::unwrap).collect();
+        assert_eq!(read, strings);
+    }
+
+    #[test]
+    fn truncation() {
+        let strings = ["hello", "", "world"];
+        let data = write_frames::<BE>(&strings);
+        let mut boundaries = vec![0];
+        for s in strings {
+            boundaries.push(boundaries.last().unwrap() + 8 + 1 + s.len());
+        }
+
+        for cut in 0..data.len() {
+            let r = FrameReader::<BE, Str, String, _>::new(ByteCursor::new(&data[..cut]));
+            let res:Vec<_> = r.collect();
+            let complete = boundaries.iter().filter(|b| **b <= cut).count() - 1;
+            assert!(res[..complete].iter().all(Result::is_ok), "cut at {}", cut);
+            if boundaries.contains(&cut) {
+                assert_eq!(res.len(), complete, "cut at {}", cut);
+            } else {
+                assert_eq!(res.len(), complete + 1, "cut at {}", cut);
+                assert!(matches!(res[complete], Err(FrameError::IO(_) | FrameError::Truncated{..})), "cut at {}", cut);
+            }
+        }
+    }
+
+    #[test]
+    fn skip_corrupt() {
+        let mut data = write_frames::<UVarInt>(&["one"]);
+        // frames with invalid utf-8, a string shorter than its frame, and a string longer than its frame
+        data.extend_from_slice(&[2, 1, 0xff]);
+        data.extend_from_slice(&[3, 1, b'a', b'b']);
+        data.extend_from_slice(&[2, 5, b'a']);
+        data.extend_from_slice(&write_frames::<UVarInt>(&["two"]));
+
+        let mut r = FrameReader::<UVarInt, Str, String, _>::new(ByteCursor::new(&data));
+        assert_eq!(r.next().unwrap().unwrap(), "one");
+        assert!(matches!(r.next(), Some(Err(FrameError::Payload(StringParseError::InvalidUtf8OwnedError(_))))));
+        assert_eq!(r.next(), Some(Err(FrameError::TrailingBytes{unused: 1})));
+        assert_eq!(r.next(), Some(Err(FrameError::Overrun)));
+        assert_eq!(r.next().unwrap().unwrap(), "two");
+        assert!(r.next().is_none());
+
+        let data = [0x80, 0x80, 0x01, b'a'];
+        let mut r = FrameReader::<UVarInt, Str, String, _>::new(ByteCursor::new(&data)).with_max_frame_size(1000);
+        assert_eq!(r.next(), Some(Err(FrameError::TooLarge{len: 16384, max: 1000})));
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn huge_length() {
+        use crate::io_wrap::BufWrapper;
+
+        let mut data = Vec::new();
+        UVarInt::byte_serialize(&(1 << 50), &mut data).unwrap();
+        data.extend_from_slice(b"not nearly that much");
+
+        let mut r = FrameReader::<UVarInt, Str, String, _>::new(BufWrapper::new(data.as_slice()));
+        assert!(matches!(r.next(), Some(Err(FrameError::TooLarge{len: 0x4_0000_0000_0000, max: DEFAULT_MAX_FRAME_SIZE}))));
+
+        // even without a limit, only as much as the input holds is buffered
+        let mut r = FrameReader::<UVarInt, Str, String, _>::new(BufWrapper::new(data.as_slice())).with_max_frame_size(u64::MAX);
+        assert!(matches!(r.next(), Some(Err(FrameError::Truncated{expected: 0x4_0000_0000_0000, found: 20}))));
+    }
+}
@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use crate::io::{ByteRead, ByteCursor, EndOfBufferError};
+use crate::serde::{ByteDeserialize, ParseOrIOError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Decoded<T> {
+    /// A value was decoded from the first `consumed` bytes
+    Complete{value: T, consumed: usize},
+    /// The input ends partway through a value, and at least `needed` more bytes are required before trying again
+    Incomplete{needed: usize},
+}
+
+/// A [`ByteCursor`] that remembers by how much its last failed read came up short
+struct ShortfallCursor<'a> {
+    inner: ByteCursor<'a>,
+    shortfall: usize,
+}
+
+impl<'a> ByteRead for ShortfallCursor<'a> {
+    type Err = EndOfBufferError;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        self.read_buf(1).map(|buf| buf[0])
+    }
+
+    fn read_buf<'b>(&'b mut self, len: u64) -> Result<Cow<'b, [u8]>, Self::Err> {
+        let len_us:usize = len.try_into().unwrap();
+        if len_us > self.inner.remaining() {
+            self.shortfall = len_us - self.inner.remaining();
+            return Err(EndOfBufferError);
+        }
+        self.inner.read_buf(len)
+    }
+
+    fn read_up_to<'b>(&'b mut self, max: u64) -> Result<Cow<'b, [u8]>, Self::Err> {
+        self.inner.read_up_to(max)
+    }
+}
+
+/// Tries to decode a value from the start of `buf`, which may hold only part of it.
+///
+/// Decoding starts over from the beginning every time, so this works with any [`ByteDeserialize`] without it keeping state of its own.
+pub fn decode_prefix<E, T>(buf: &[u8]) -> Result<Decoded<T>, E::ParseErr>
+where
+    E: ByteDeserialize<T>,
+{
+    let mut cur = ShortfallCursor{inner: ByteCursor::new(buf), shortfall: 0};
+    match E::byte_deserialize(&mut cur) {
+        Ok(value) => Ok(Decoded::Complete{value, consumed: cur.inner.position()}),
+        Err(ParseOrIOError::Parse(e)) => Err(e),
+        Err(ParseOrIOError::IO(EndOfBufferError)) => Ok(Decoded::Incomplete{needed: cur.shortfall.max(1)}),
+    }
+}
+
+/// Decodes a stream of values from input that arrives in pieces, such as from a non-blocking socket.
+///
+/// Input is handed over with [`IncrementalDecoder::feed`], and [`IncrementalDecoder::decode`] doesn't bother retrying until at least as many bytes as it last asked for have arrived.
+#[derive(Debug, Clone)]
+pub struct IncrementalDecoder<E, T> {
+    buf: Vec<u8>,
+    pos: usize,
+    needed: usize,
+    encoder: PhantomData<E>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<E, T> Default for IncrementalDecoder<E, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, T> IncrementalDecoder<E, T> {
+    pub fn new() -> Self {
+        Self{
+            buf: Vec::new(),
+            pos: 0,
+            needed: 0,
+            encoder: PhantomData,
+            value: PhantomData,
+        }
+    }
+
+    /// Drops whatever has already been decoded and appends `data` to the rest.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(data);
+    }
+
+    /// The bytes that have been fed in but not decoded yet
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// How many more bytes are needed at least before a value could be decoded
+    pub fn needed(&self) -> usize {
+        self.needed.saturating_sub(self.buffered().len())
+    }
+}
+
+impl<E, T> IncrementalDecoder<E, T>
+where
+    E: ByteDeserialize<T>,
+{
+    /// Decodes the next value if all of it has been fed in, or else returns how many more bytes are needed at least.
+    ///
+    /// After a parse error the offending bytes are still buffered, so the same error will happen again.
+    pub fn decode(&mut self) -> Result<Decoded<T>, E::ParseErr> {
+        let available = self.buffered().len();
+        if available < self.needed {
+            return Ok(Decoded::Incomplete{needed: self.needed - available});
+        }
+        let res = decode_prefix::<E, T>(self.buffered())?;
+        match res {
+            Decoded::Complete{consumed, ..} => {
+                self.pos += consumed;
+                self.needed = 0;
+            },
+            Decoded::Incomplete{needed} => {
+                self.needed = available + needed;
+            },
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serde::ByteSerialize;
+    use crate::varint::{UVarInt, VarIntTooBig};
+    use crate::lists::LengthPrefixList;
+    use crate::string::LengthPrefixString;
+
+    type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+    #[test]
+    fn blarg() {
+        let messages = [
+            vec!["hello".to_string(), "world".to_string()],
+            vec![],
+            vec!["x".repeat(300)],
+        ];
+        let mut data = Vec::new();
+        for m in messages.iter() {
+            Strings::byte_serialize(m, &mut data).unwrap();
+        }
+
+        for chunk_size in [1, 2, 3, 7, 100, data.len()] {
+            let mut dec = IncrementalDecoder::<Strings, Vec<String>>::new();
+            let mut decoded = Vec::new();
+            for chunk in data.chunks(chunk_size) {
+                dec.feed(chunk);
+                while let Decoded::Complete{value, ..} = dec.decode().unwrap() {
+                    decoded.push(value);
+                }
+            }
+            assert_eq!(decoded, messages);
+            assert!(dec.buffered().is_empty());
+        }
+    }
+
+    #[test]
+    fn misaligned_chunks() {
+        use crate::endians::NumberEncodingBE as BE;
+
+        let mut data = Vec::new();
+        for n in 0..1000u64 {
+            BE::byte_serialize(&n, &mut data).unwrap();
+        }
+        // every chunk ends one byte into the next value
+        let mut dec = IncrementalDecoder::<BE, u64>::new();
+        let mut next = 0;
+        for chunk in std::iter::once(&data[..1]).chain(data[1..].chunks(8)) {
+            dec.feed(chunk);
+            assert!(dec.buf.len() <= 16, "{} bytes buffered", dec.buf.len());
+            while let Decoded::Complete{value, ..} = dec.decode().unwrap() {
+                assert_eq!(value, next);
+                next += 1;
+            }
+        }
+        assert_eq!(next, 1000);
+    }
+
+    #[test]
+    fn needed() {
+        let mut dec = IncrementalDecoder::<LengthPrefixString<UVarInt>, String>::new();
+        assert_eq!(dec.decode(), Ok(Decoded::Incomplete{needed: 1}));
+        dec.feed(&[0x80]);
+        assert_eq!(dec.decode(), Ok(Decoded::Incomplete{needed: 1}));
+        dec.feed(&[0x01]);
+        // the length is known now, so all 128 bytes of the string are needed
+        assert_eq!(dec.decode(), Ok(Decoded::Incomplete{needed: 128}));
+        dec.feed(&[b'a'; 100]);
+        assert_eq!(dec.needed(), 28);
+        assert_eq!(dec.decode(), Ok(Decoded::Incomplete{needed: 28}));
+        dec.feed(&[b'a'; 28]);
+        assert_eq!(dec.decode(), Ok(Decoded::Complete{value: "a".repeat(128), consumed: 130}));
+
+        assert_eq!(decode_prefix::<UVarInt, u64>(&[0xff; 11]), Err(VarIntTooBig));
+        dec.feed(&[1, 0xff]);
+        assert!(matches!(dec.decode(), Err(crate::string::StringParseError::InvalidUtf8OwnedError(_))));
+    }
+}
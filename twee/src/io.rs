@@ -59,6 +59,35 @@ pub trait ByteRead {
     }
 
     fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err>;
+
+    /// Reads `max` bytes, or everything that's left if there are fewer than that. Only returns a short buffer at the end of the input.
+    ///
+    /// The default implementation reads a byte at a time and can't tell the end of the input from a failed read, so it stops at the first error: it returns what it read before that, or the error if that was nothing. Readers that know where their input ends should override this.
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let mut res = Vec::new();
+        while (res.len() as u64) < max {
+            match self.read_byte() {
+                Ok(b) => res.push(b),
+                Err(e) if res.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(res.into())
+    }
+
+    /// Reads past `len` bytes without looking at them. Fails if there aren't that many left.
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        self.read_buf(len).map(drop)
+    }
+}
+
+/// A [`ByteWrite`] that can go back and overwrite what it has already written, such as to fill in a length once it's known.
+pub trait SeekableByteWrite: ByteWrite {
+    /// How many bytes have been written so far, or for a stream, the current offset into it
+    fn position(&mut self) -> Result<u64, Self::Err>;
+
+    /// Overwrites already-written bytes starting at `pos`, leaving the position where it was.
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), Self::Err>;
 }
 
 impl ByteWrite for Vec<u8> {
@@ -75,6 +104,19 @@ impl ByteWrite for Vec<u8> {
     }
 }
 
+/// Panics if asked to patch past the end of what has been written.
+impl SeekableByteWrite for Vec<u8> {
+    fn position(&mut self) -> Result<u64, !> {
+        Ok(self.len() as u64)
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), !> {
+        let pos:usize = pos.try_into().unwrap();
+        self[pos .. pos + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+}
+
 pub struct ByteCursor<'a> {
     idx: usize,
     inner: &'a [u8],
@@ -91,9 +133,48 @@ impl<'a> ByteCursor<'a> {
     pub fn at_end(&self) -> bool {
         self.idx == self.inner.len()
     }
+
+    /// How many bytes have been consumed so far
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.inner.len() - self.idx
+    }
+
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        &self.inner[self.idx..]
+    }
+
+    /// Moves the cursor to `pos`, which may be anywhere up to and including the end of the buffer. Useful for rewinding after a failed speculative parse.
+    pub fn set_position(&mut self, pos: usize) -> Result<(), EndOfBufferError> {
+        if pos > self.inner.len() {
+            return Err(EndOfBufferError);
+        }
+        self.idx = pos;
+        Ok(())
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), EndOfBufferError> {
+        if len > self.remaining() {
+            return Err(EndOfBufferError);
+        }
+        self.idx += len;
+        Ok(())
+    }
+
+    /// Splits off the next `len` bytes as their own cursor, and advances this cursor past them.
+    ///
+    /// The returned cursor starts at position 0 and refuses to read past the end of its `len` bytes.
+    pub fn split_sub_cursor(&mut self, len: usize) -> Result<ByteCursor<'a>, EndOfBufferError> {
+        let start = self.idx;
+        self.skip(len)?;
+        Ok(ByteCursor::new(&self.inner[start..self.idx]))
+    }
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct EndOfBufferError;
 
 impl std::fmt::Display for EndOfBufferError {
@@ -122,6 +203,512 @@ impl<'a> ByteRead for ByteCursor<'a> {
         self.idx += 1;
         Ok(*res)
     }
+
+    fn read_up_to<'b>(&'b mut self, max: u64) -> Result<Cow<'b, [u8]>, Self::Err> {
+        let len = max.min(self.remaining().try_into().unwrap());
+        self.read_buf(len)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        self.skip(len.try_into().map_err(|_| EndOfBufferError)?)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct OutOfSpaceError;
+
+impl std::fmt::Display for OutOfSpaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "attempted to write past end of buffer")
+    }
+}
+
+impl std::error::Error for OutOfSpaceError {}
+
+/// Writes into a fixed-size buffer, such as a stack array.
+///
+/// A write that doesn't fit fails with [`OutOfSpaceError`] without writing anything.
+#[derive(Debug)]
+pub struct SliceWriter<'a> {
+    idx: usize,
+    inner: &'a mut [u8],
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(inner: &'a mut [u8]) -> Self {
+        Self{
+            idx: 0,
+            inner,
+        }
+    }
+
+    /// How many bytes have been written so far
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.inner.len() - self.idx
+    }
+
+    pub fn written(&self) -> &[u8] {
+        &self.inner[..self.idx]
+    }
+
+    pub fn into_written(self) -> &'a mut [u8] {
+        &mut self.inner[..self.idx]
+    }
+}
+
+impl<'a> ByteWrite for SliceWriter<'a> {
+    type Err = OutOfSpaceError;
+
+    fn write_byte(&mut self, data: u8) -> Result<(), Self::Err> {
+        let slot = self.inner.get_mut(self.idx).ok_or(OutOfSpaceError)?;
+        *slot = data;
+        self.idx += 1;
+        Ok(())
+    }
+
+    fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        if data.len() > self.remaining() {
+            return Err(OutOfSpaceError);
+        }
+        self.inner[self.idx .. self.idx + data.len()].copy_from_slice(data);
+        self.idx += data.len();
+        Ok(())
+    }
+}
+
+/// Fails without writing anything if asked to patch past the end of what has been written.
+impl<'a> SeekableByteWrite for SliceWriter<'a> {
+    fn position(&mut self) -> Result<u64, Self::Err> {
+        Ok(self.idx as u64)
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), Self::Err> {
+        let end = usize::try_from(pos).ok().and_then(|pos| pos.checked_add(data.len()));
+        match end {
+            Some(end) if end <= self.idx => {
+                self.inner[end - data.len() .. end].copy_from_slice(data);
+                Ok(())
+            },
+            _ => Err(OutOfSpaceError),
+        }
+    }
+}
+
+impl<W: ByteWrite + ?Sized> ByteWrite for &mut W {
+    type Err = W::Err;
+
+    fn write_byte(&mut self, data: u8) -> Result<(), Self::Err> {
+        (**self).write_byte(data)
+    }
+
+    fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        (**self).write_buf(data)
+    }
+}
+
+impl<W: SeekableByteWrite + ?Sized> SeekableByteWrite for &mut W {
+    fn position(&mut self) -> Result<u64, Self::Err> {
+        (**self).position()
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), Self::Err> {
+        (**self).patch(pos, data)
+    }
+}
+
+impl<W: ByteWrite + ?Sized> ByteWrite for Box<W> {
+    type Err = W::Err;
+
+    fn write_byte(&mut self, data: u8) -> Result<(), Self::Err> {
+        (**self).write_byte(data)
+    }
+
+    fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        (**self).write_buf(data)
+    }
+}
+
+impl<W: SeekableByteWrite + ?Sized> SeekableByteWrite for Box<W> {
+    fn position(&mut self) -> Result<u64, Self::Err> {
+        (**self).position()
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), Self::Err> {
+        (**self).patch(pos, data)
+    }
+}
+
+impl<R: ByteRead + ?Sized> ByteRead for &mut R {
+    type Err = R::Err;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        (**self).read_byte()
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        (**self).read_buf(len)
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        (**self).read_up_to(max)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        (**self).skip_buf(len)
+    }
+}
+
+impl<R: ByteRead + ?Sized> ByteRead for Box<R> {
+    type Err = R::Err;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        (**self).read_byte()
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        (**self).read_buf(len)
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        (**self).read_up_to(max)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        (**self).skip_buf(len)
+    }
+}
+
+/// Limits a reader to at most `limit` bytes. Reading past the limit fails with [`TakeError::LimitExceeded`] and consumes nothing.
+#[derive(Debug, Clone)]
+pub struct Take<R> {
+    inner: R,
+    limit: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TakeError<E> {
+    LimitExceeded{requested: u64, remaining: u64},
+    Inner(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for TakeError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LimitExceeded{requested, remaining} => write!(f, "attempted to read {} bytes with only {} left before the limit", requested, remaining),
+            Self::Inner(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for TakeError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LimitExceeded{..} => None,
+            Self::Inner(e) => Some(e),
+        }
+    }
+}
+
+impl<R> Take<R> {
+    pub fn new(inner: R, limit: u64) -> Self {
+        Self{inner, limit}
+    }
+
+    /// How many bytes can still be read
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ByteRead> ByteRead for Take<R> {
+    type Err = TakeError<R::Err>;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        if self.limit == 0 {
+            return Err(TakeError::LimitExceeded{requested: 1, remaining: 0});
+        }
+        let res = self.inner.read_byte().map_err(TakeError::Inner)?;
+        self.limit -= 1;
+        Ok(res)
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        if len > self.limit {
+            return Err(TakeError::LimitExceeded{requested: len, remaining: self.limit});
+        }
+        let res = self.inner.read_buf(len).map_err(TakeError::Inner)?;
+        self.limit -= len;
+        Ok(res)
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let res = self.inner.read_up_to(max.min(self.limit)).map_err(TakeError::Inner)?;
+        self.limit -= res.len() as u64;
+        Ok(res)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        if len > self.limit {
+            return Err(TakeError::LimitExceeded{requested: len, remaining: self.limit});
+        }
+        self.inner.skip_buf(len).map_err(TakeError::Inner)?;
+        self.limit -= len;
+        Ok(())
+    }
+}
+
+/// Reads everything from `first`, then continues with `second`.
+///
+/// Finding the end of `first` relies on its [`ByteRead::read_up_to`].
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+    first_done: bool,
+    /// The end of `first`, kept back after a read spanning both readers failed in `second`
+    leftover: Vec<u8>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChainError<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A: std::fmt::Display, B: std::fmt::Display> std::fmt::Display for ChainError<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::First(e) => e.fmt(f),
+            Self::Second(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<A, B> std::error::Error for ChainError<A, B>
+where
+    A: std::error::Error + 'static,
+    B: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::First(e) => Some(e),
+            Self::Second(e) => Some(e),
+        }
+    }
+}
+
+impl<A, B> Chain<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self{first, second, first_done: false, leftover: Vec::new()}
+    }
+
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: ByteRead, B: ByteRead> ByteRead for Chain<A, B> {
+    type Err = ChainError<A::Err, B::Err>;
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        if !self.first_done {
+            let head = self.first.read_up_to(len).map_err(ChainError::First)?;
+            if head.len() as u64 == len {
+                return Ok(head);
+            }
+            self.leftover = head.into_owned();
+            self.first_done = true;
+        }
+        let have = self.leftover.len() as u64;
+        if have == 0 {
+            return self.second.read_buf(len).map_err(ChainError::Second);
+        }
+        if len <= have {
+            return Ok(self.leftover.drain(..len as usize).collect::<Vec<_>>().into());
+        }
+        let tail = self.second.read_buf(len - have).map_err(ChainError::Second)?;
+        let mut res = std::mem::take(&mut self.leftover);
+        res.extend_from_slice(&tail);
+        Ok(res.into())
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        if !self.first_done {
+            let head = self.first.read_up_to(max).map_err(ChainError::First)?;
+            if head.len() as u64 == max {
+                return Ok(head);
+            }
+            self.leftover = head.into_owned();
+            self.first_done = true;
+        }
+        let have = self.leftover.len() as u64;
+        if have == 0 {
+            return self.second.read_up_to(max).map_err(ChainError::Second);
+        }
+        if max <= have {
+            return Ok(self.leftover.drain(..max as usize).collect::<Vec<_>>().into());
+        }
+        let tail = self.second.read_up_to(max - have).map_err(ChainError::Second)?;
+        let mut res = std::mem::take(&mut self.leftover);
+        res.extend_from_slice(&tail);
+        Ok(res.into())
+    }
+}
+
+/// Copies every byte read from `inner` into `out`.
+#[derive(Debug, Clone)]
+pub struct Tee<R, W> {
+    inner: R,
+    out: W,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TeeError<R, W> {
+    Read(R),
+    Write(W),
+}
+
+impl<R: std::fmt::Display, W: std::fmt::Display> std::fmt::Display for TeeError<R, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(e) => e.fmt(f),
+            Self::Write(e) => write!(f, "failed to copy read bytes: {}", e),
+        }
+    }
+}
+
+impl<R, W> std::error::Error for TeeError<R, W>
+where
+    R: std::error::Error + 'static,
+    W: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read(e) => Some(e),
+            Self::Write(e) => Some(e),
+        }
+    }
+}
+
+impl<R, W> Tee<R, W> {
+    pub fn new(inner: R, out: W) -> Self {
+        Self{inner, out}
+    }
+
+    pub fn get_ref(&self) -> (&R, &W) {
+        (&self.inner, &self.out)
+    }
+
+    pub fn get_mut(&mut self) -> (&mut R, &mut W) {
+        (&mut self.inner, &mut self.out)
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.inner, self.out)
+    }
+}
+
+impl<R: ByteRead, W: ByteWrite> ByteRead for Tee<R, W> {
+    type Err = TeeError<R::Err, W::Err>;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        let res = self.inner.read_byte().map_err(TeeError::Read)?;
+        self.out.write_byte(res).map_err(TeeError::Write)?;
+        Ok(res)
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let res = self.inner.read_buf(len).map_err(TeeError::Read)?;
+        self.out.write_buf(&res).map_err(TeeError::Write)?;
+        Ok(res)
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let res = self.inner.read_up_to(max).map_err(TeeError::Read)?;
+        self.out.write_buf(&res).map_err(TeeError::Write)?;
+        Ok(res)
+    }
+}
+
+/// Keeps track of how many bytes have been read through it.
+#[derive(Debug, Clone)]
+pub struct Counting<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R> Counting<R> {
+    pub fn new(inner: R) -> Self {
+        Self{inner, position: 0}
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ByteRead> ByteRead for Counting<R> {
+    type Err = R::Err;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        let res = self.inner.read_byte()?;
+        self.position += 1;
+        Ok(res)
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let res = self.inner.read_buf(len)?;
+        self.position += len;
+        Ok(res)
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let res = self.inner.read_up_to(max)?;
+        self.position += res.len() as u64;
+        Ok(res)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        self.inner.skip_buf(len)?;
+        self.position += len;
+        Ok(())
+    }
 }
 
 #[derive(Debug,Copy,Clone,Default)]
@@ -142,4 +729,139 @@ impl ByteWrite for ByteCounter {
         self.count += len;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl SeekableByteWrite for ByteCounter {
+    fn position(&mut self) -> Result<u64, !> {
+        Ok(self.count)
+    }
+
+    fn patch(&mut self, _pos: u64, _data: &[u8]) -> Result<(), !> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serde::ByteDeserialize;
+    use crate::varint::UVarInt;
+    use crate::endians::NumberEncodingBE as BE;
+
+    #[test]
+    fn blarg() {
+        let data = [0x81, 0x01, 3, 0xaa, 0xbb, 0xcc, 7];
+        let mut cur = ByteCursor::new(&data);
+        let n:u64 = UVarInt::byte_deserialize(&mut cur).unwrap();
+        assert_eq!(n, 129);
+        assert_eq!(cur.position(), 2);
+        assert_eq!(cur.remaining(), 5);
+
+        let len:u8 = BE::byte_deserialize(&mut cur).unwrap();
+        let mut sub = cur.split_sub_cursor(len.into()).unwrap();
+        assert_eq!(cur.position(), 6);
+        assert_eq!(cur.remaining_slice(), &[7]);
+        assert_eq!(sub.remaining_slice(), &[0xaa, 0xbb, 0xcc]);
+        assert!(sub.read_buf(4).is_err());
+        assert_eq!(sub.read_buf(3).unwrap().as_ref(), &[0xaa, 0xbb, 0xcc]);
+        assert!(sub.at_end());
+        assert!(sub.read_byte().is_err());
+
+        // rewind after a failed speculative parse
+        let before = cur.position();
+        assert!(<BE as ByteDeserialize<u16>>::byte_deserialize(&mut cur).is_err());
+        cur.set_position(before).unwrap();
+        assert_eq!(cur.read_byte().unwrap(), 7);
+
+        assert!(cur.split_sub_cursor(1).is_err());
+        assert!(cur.skip(1).is_err());
+        assert!(cur.set_position(data.len() + 1).is_err());
+        cur.set_position(0).unwrap();
+        cur.skip(data.len()).unwrap();
+        assert!(cur.at_end());
+    }
+
+    #[test]
+    fn slice_writer() {
+        use crate::serde::ByteSerialize;
+
+        fn write_twice<W: ByteWrite>(mut io: W) -> Result<(), W::Err> {
+            UVarInt::byte_serialize(&300, &mut io)?;
+            BE::byte_serialize(&0x0102u16, &mut io)
+        }
+
+        let mut buf = [0u8; 6];
+        let mut w = SliceWriter::new(&mut buf);
+        write_twice(&mut w).unwrap();
+        assert_eq!(w.position(), 4);
+        assert_eq!(w.written(), &[0xac, 0x02, 0x01, 0x02]);
+        assert!(BE::byte_serialize(&7u32, &mut w).is_err());
+        assert_eq!(w.position(), 4);
+        write_twice(Box::new(&mut w)).unwrap_err();
+        assert_eq!(w.into_written(), &[0xac, 0x02, 0x01, 0x02, 0xac, 0x02]);
+
+        let mut cur = ByteCursor::new(&buf);
+        let n:u64 = UVarInt::byte_deserialize(&mut &mut cur).unwrap();
+        assert_eq!(n, 300);
+        let mut boxed:Box<dyn ByteRead<Err = EndOfBufferError>> = Box::new(cur);
+        let n:u16 = BE::byte_deserialize(&mut boxed).unwrap();
+        assert_eq!(n, 0x0102);
+    }
+
+    #[test]
+    fn adapters() {
+        use crate::io_wrap::BufWrapper;
+        use crate::lists::LengthPrefixList;
+        use crate::string::LengthPrefixString;
+        type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+        let header = [2, 3, b'a', b'b'];
+        let body = [b'c', 2, b'h', b'i', 0xee];
+
+        // the second string is split across the two readers
+        let chain = Chain::new(ByteCursor::new(&header), BufWrapper::new(body.as_slice()));
+        let mut counting = Counting::new(Tee::new(chain, Vec::new()));
+        let strings:Vec<String> = Strings::byte_deserialize(&mut counting).unwrap();
+        assert_eq!(strings, ["abc", "hi"]);
+        assert_eq!(counting.position(), 8);
+        assert_eq!(counting.read_up_to(5).unwrap().as_ref(), &[0xee]);
+        assert_eq!(counting.read_up_to(5).unwrap().as_ref(), &[]);
+        let (_, consumed) = counting.into_inner().into_inner();
+        assert_eq!(consumed, [2, 3, b'a', b'b', b'c', 2, b'h', b'i', 0xee]);
+
+        let data = [0x81, 0x01, 3];
+        let mut take = Take::new(BufWrapper::new(data.as_slice()), 2);
+        let n:u64 = UVarInt::byte_deserialize(&mut take).unwrap();
+        assert_eq!(n, 129);
+        assert_eq!(take.limit(), 0);
+        let e = <BE as ByteDeserialize<u8>>::byte_deserialize(&mut take).unwrap_err();
+        assert!(matches!(e, crate::serde::ParseOrIOError::IO(TakeError::LimitExceeded{requested: 1, remaining: 0})));
+        assert_eq!(take.into_inner().read_byte().unwrap(), 3);
+
+        // a read that fails in the second reader doesn't lose the end of the first
+        let mut chain = Chain::new(ByteCursor::new(&[1, 2]), ByteCursor::new(&[3]));
+        assert!(matches!(chain.read_buf(4), Err(ChainError::Second(EndOfBufferError))));
+        assert_eq!(chain.read_buf(1).unwrap().as_ref(), &[1]);
+        assert_eq!(chain.read_up_to(5).unwrap().as_ref(), &[2, 3]);
+    }
+
+    #[test]
+    fn default_read_up_to() {
+        /// Only implements the required methods
+        struct Minimal<'a>(ByteCursor<'a>);
+
+        impl ByteRead for Minimal<'_> {
+            type Err = EndOfBufferError;
+
+            fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+                self.0.read_buf(len)
+            }
+        }
+
+        let mut r = Minimal(ByteCursor::new(&[1, 2, 3]));
+        assert_eq!(r.read_up_to(2).unwrap().as_ref(), &[1, 2]);
+        assert_eq!(r.read_up_to(5).unwrap().as_ref(), &[3]);
+        assert_eq!(r.read_up_to(5), Err(EndOfBufferError));
+        assert_eq!(r.read_up_to(0).unwrap().as_ref(), &[]);
+    }
+}
@@ -1,12 +1,17 @@
 use std::io as stdio;
+use std::io::Read;
 use std::borrow::Cow;
 
-use crate::io::{ByteRead, ByteWrite};
+use crate::io::{ByteRead, ByteWrite, SeekableByteWrite};
 
+/// An unbuffered wrapper around a [`std::io`] reader or writer.
+///
+/// Every [`ByteRead::read_buf`] allocates, and every [`ByteWrite::write_byte`] is a separate call to the inner writer, which for a [`std::fs::File`] or a socket means a syscall per byte.
+#[deprecated(note = "allocates on every read and writes byte by byte; use `BufWrapper` instead")]
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct StdWrapper<V> (pub V);
 
-
+#[allow(deprecated)]
 impl<V: stdio::Write> ByteWrite for StdWrapper<V> {
     type Err = stdio::Error;
 
@@ -19,12 +24,357 @@ impl<V: stdio::Write> ByteWrite for StdWrapper<V> {
     }
 }
 
+#[allow(deprecated)]
 impl<V: stdio::Read> ByteRead for StdWrapper<V> {
     type Err = stdio::Error;
 
     fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
-        let mut buf:Vec<u8> = (0..len).map(|_| 0).collect();
+        let mut buf = vec![0; len.try_into().unwrap()];
         self.0.read_exact(buf.as_mut_slice())?;
         Ok(buf.into())
     }
-}
\ No newline at end of file
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let mut buf = Vec::new();
+        stdio::Read::take(&mut self.0, max).read_to_end(&mut buf)?;
+        Ok(buf.into())
+    }
+}
+
+const DEFAULT_CAPACITY:usize = 8 * 1024;
+
+/// Makes a [`std::io`] reader or writer into a [`ByteRead`] or [`ByteWrite`].
+///
+/// Reads are served out of an internal buffer, so [`ByteRead::read_buf`] returns borrowed slices and never allocates unless asked for more than the buffer can hold. Writes are collected in a separate buffer and handed to the inner writer in large chunks. Unlike [`std::io::BufWriter`], nothing is written on drop: call [`BufWrapper::flush`] or [`BufWrapper::into_inner`] to push pending writes out.
+///
+/// Each buffer is only allocated once it's first used.
+pub struct BufWrapper<V> {
+    inner: V,
+    capacity: usize,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    read_filled: usize,
+    write_buf: Vec<u8>,
+}
+
+impl<V> BufWrapper<V> {
+    pub fn new(inner: V) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: V) -> Self {
+        Self{
+            inner,
+            capacity: capacity.max(1),
+            read_buf: Vec::new(),
+            read_pos: 0,
+            read_filled: 0,
+            write_buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Reading from or writing to the inner value directly will skip over anything currently buffered.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    /// The bytes that have been read from the inner reader but not yet consumed
+    pub fn buffer(&self) -> &[u8] {
+        &self.read_buf[self.read_pos..self.read_filled]
+    }
+
+    /// Returns the inner value and any writes that haven't been handed to it yet. Any buffered but unconsumed read data is lost.
+    pub fn into_parts(self) -> (V, Vec<u8>) {
+        (self.inner, self.write_buf)
+    }
+}
+
+impl<V: stdio::Write> BufWrapper<V> {
+    /// Writes out everything buffered so far and flushes the inner writer.
+    pub fn flush(&mut self) -> stdio::Result<()> {
+        self.write_pending()?;
+        self.inner.flush()
+    }
+
+    /// Writes out pending writes and returns the inner value, without flushing it. Any buffered but unconsumed read data is lost.
+    pub fn into_inner(mut self) -> stdio::Result<V> {
+        self.write_pending()?;
+        Ok(self.inner)
+    }
+
+    /// Hands the write buffer to the inner writer. Whatever it didn't take stays buffered, even on error.
+    fn write_pending(&mut self) -> stdio::Result<()> {
+        let mut written = 0;
+        let res = loop {
+            if written == self.write_buf.len() {
+                break Ok(());
+            }
+            match self.inner.write(&self.write_buf[written..]) {
+                Ok(0) => break Err(stdio::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == stdio::ErrorKind::Interrupted => (),
+                Err(e) => break Err(e),
+            }
+        };
+        self.write_buf.drain(..written);
+        res
+    }
+}
+
+impl<V: std::fmt::Debug> std::fmt::Debug for BufWrapper<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufWrapper")
+            .field("inner", &self.inner)
+            .field("buffered_read", &(self.read_filled - self.read_pos))
+            .field("buffered_write", &self.write_buf.len())
+            .finish()
+    }
+}
+
+impl<V: stdio::Write> ByteWrite for BufWrapper<V> {
+    type Err = stdio::Error;
+
+    fn write_byte(&mut self, data: u8) -> Result<(), Self::Err> {
+        self.write_buf(&[data])
+    }
+
+    fn write_buf(&mut self, data: &[u8]) -> Result<(), Self::Err> {
+        if self.write_buf.len() + data.len() > self.capacity {
+            self.write_pending()?;
+        }
+        if data.len() >= self.capacity {
+            self.inner.write_all(data)
+        } else {
+            self.write_buf.reserve_exact(self.capacity - self.write_buf.len());
+            self.write_buf.extend_from_slice(data);
+            Ok(())
+        }
+    }
+}
+
+/// Patching anything that has already been handed to the inner writer flushes the buffer and seeks back and forth.
+impl<V: stdio::Write + stdio::Seek> SeekableByteWrite for BufWrapper<V> {
+    fn position(&mut self) -> Result<u64, Self::Err> {
+        Ok(self.inner.stream_position()? + self.write_buf.len() as u64)
+    }
+
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<(), Self::Err> {
+        let flushed = self.inner.stream_position()?;
+        let written = flushed + self.write_buf.len() as u64;
+        if pos.checked_add(data.len() as u64).is_none_or(|end| end > written) {
+            return Err(stdio::Error::new(
+                stdio::ErrorKind::InvalidInput,
+                format!("patch of {} bytes at {} runs past the {} bytes written", data.len(), pos, written),
+            ));
+        }
+        if pos >= flushed {
+            let start:usize = (pos - flushed).try_into().unwrap();
+            self.write_buf[start .. start + data.len()].copy_from_slice(data);
+            return Ok(());
+        }
+        self.write_pending()?;
+        let end = self.inner.stream_position()?;
+        self.inner.seek(stdio::SeekFrom::Start(pos))?;
+        self.inner.write_all(data)?;
+        self.inner.seek(stdio::SeekFrom::Start(end))?;
+        Ok(())
+    }
+}
+
+impl<V: stdio::Read> BufWrapper<V> {
+    /// Reads from the inner reader until at least `len` bytes are buffered, or the reader runs out. Returns whether `len` bytes are available.
+    fn fill_to(&mut self, len: usize) -> stdio::Result<bool> {
+        if self.read_filled - self.read_pos >= len {
+            return Ok(true);
+        }
+        self.read_buf.copy_within(self.read_pos..self.read_filled, 0);
+        self.read_filled -= self.read_pos;
+        self.read_pos = 0;
+        if self.read_buf.len() < self.capacity {
+            self.read_buf.resize(self.capacity, 0);
+        }
+        while self.read_filled < len {
+            if self.read_filled == self.read_buf.len() {
+                // At most double the buffer at a time, so a bogus length only costs as much memory as the input that actually arrives
+                let grow = (len - self.read_filled).min(self.read_buf.len());
+                self.read_buf.resize(self.read_buf.len() + grow, 0);
+            }
+            match self.inner.read(&mut self.read_buf[self.read_filled..]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.read_filled += n,
+                Err(e) if e.kind() == stdio::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<V: stdio::Read> ByteRead for BufWrapper<V> {
+    type Err = stdio::Error;
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        if self.read_pos == self.read_filled && !self.fill_to(1)? {
+            return Err(stdio::ErrorKind::UnexpectedEof.into());
+        }
+        let res = self.read_buf[self.read_pos];
+        self.read_pos += 1;
+        Ok(res)
+    }
+
+    fn read_buf<'a>(&'a mut self, len: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let len_us:usize = len.try_into().unwrap();
+        if !self.fill_to(len_us)? {
+            return Err(stdio::ErrorKind::UnexpectedEof.into());
+        }
+        let res = &self.read_buf[self.read_pos .. self.read_pos + len_us];
+        self.read_pos += len_us;
+        Ok(res.into())
+    }
+
+    fn read_up_to<'a>(&'a mut self, max: u64) -> Result<Cow<'a, [u8]>, Self::Err> {
+        let max_us:usize = max.try_into().unwrap();
+        let len = if self.fill_to(max_us)? { max_us } else { self.read_filled - self.read_pos };
+        let res = &self.read_buf[self.read_pos .. self.read_pos + len];
+        self.read_pos += len;
+        Ok(res.into())
+    }
+
+    /// Skips anything past the buffer by reading it in chunks, so the buffer doesn't grow.
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        let buffered = (self.read_filled - self.read_pos) as u64;
+        if len <= buffered {
+            self.read_pos += len as usize;
+            return Ok(());
+        }
+        self.read_pos = 0;
+        self.read_filled = 0;
+        let rest = len - buffered;
+        let skipped = stdio::copy(&mut stdio::Read::take(&mut self.inner, rest), &mut stdio::sink())?;
+        if skipped < rest {
+            return Err(stdio::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serde::{ByteSerialize, ByteDeserialize};
+    use crate::varint::UVarInt;
+
+    /// Counts how many times the inner reader or writer is called
+    struct Syscalls<V> {
+        inner: V,
+        calls: usize,
+    }
+
+    impl<V: stdio::Read> stdio::Read for Syscalls<V> {
+        fn read(&mut self, buf: &mut [u8]) -> stdio::Result<usize> {
+            self.calls += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl<V: stdio::Write> stdio::Write for Syscalls<V> {
+        fn write(&mut self, buf: &[u8]) -> stdio::Result<usize> {
+            self.calls += 1;
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> stdio::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    #[test]
+    fn blarg() {
+        let nums:Vec<u64> = (0..10_000).map(|n| n * 7919).collect();
+
+        let mut w = BufWrapper::new(Syscalls{inner: Vec::new(), calls: 0});
+        for n in nums.iter() {
+            UVarInt::byte_serialize(n, &mut w).unwrap();
+        }
+        let sink = w.into_inner().unwrap();
+        assert!(sink.calls < 10, "{} writes", sink.calls);
+
+        let mut r = BufWrapper::new(Syscalls{inner: sink.inner.as_slice(), calls: 0});
+        for n in nums.iter() {
+            let got:u64 = UVarInt::byte_deserialize(&mut r).unwrap();
+            assert_eq!(got, *n);
+        }
+        assert!(r.read_byte().is_err());
+        assert!(r.get_ref().calls < 10, "{} reads", r.get_ref().calls);
+    }
+
+    #[test]
+    fn borrowed_and_oversized_reads() {
+        let data:Vec<u8> = (0..=255).collect();
+        let mut r = BufWrapper::with_capacity(16, data.as_slice());
+        assert!(matches!(r.read_buf(10).unwrap(), Cow::Borrowed(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9])));
+        assert_eq!(r.read_buf(100).unwrap().as_ref(), &data[10..110]);
+        assert_eq!(r.read_byte().unwrap(), 110);
+        let e = r.read_buf(200).unwrap_err();
+        assert_eq!(e.kind(), stdio::ErrorKind::UnexpectedEof);
+
+        let mut r = BufWrapper::with_capacity(16, data.as_slice());
+        assert_eq!(r.read_byte().unwrap(), 0);
+        r.skip_buf(5).unwrap();
+        assert_eq!(r.read_byte().unwrap(), 6);
+        // skipping past the buffer doesn't grow it
+        r.skip_buf(200).unwrap();
+        assert_eq!(r.read_byte().unwrap(), 207);
+        assert_eq!(r.read_buf.len(), 16);
+        assert_eq!(r.skip_buf(100).unwrap_err().kind(), stdio::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn patch_bounds() {
+        let mut w = BufWrapper::with_capacity(4, stdio::Cursor::new(Vec::new()));
+        w.write_buf(b"abcdef").unwrap();
+        w.write_buf(b"gh").unwrap();
+        w.patch(6, b"GH").unwrap();
+        w.patch(1, b"B").unwrap();
+        assert_eq!(w.patch(7, b"HI").unwrap_err().kind(), stdio::ErrorKind::InvalidInput);
+        assert_eq!(w.patch(5, b"FGHI").unwrap_err().kind(), stdio::ErrorKind::InvalidInput);
+        assert_eq!(w.patch(u64::MAX, b"x").unwrap_err().kind(), stdio::ErrorKind::InvalidInput);
+        assert_eq!(w.into_inner().unwrap().into_inner(), b"aBcdefGH");
+    }
+
+    #[test]
+    fn failed_write_keeps_buffer() {
+        /// Takes `room` bytes, then fails
+        struct Flaky {
+            out: Vec<u8>,
+            room: usize,
+        }
+
+        impl stdio::Write for Flaky {
+            fn write(&mut self, buf: &[u8]) -> stdio::Result<usize> {
+                if self.room == 0 {
+                    return Err(stdio::ErrorKind::BrokenPipe.into());
+                }
+                let n = buf.len().min(self.room);
+                self.out.extend_from_slice(&buf[..n]);
+                self.room -= n;
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> stdio::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut w = BufWrapper::new(Flaky{out: Vec::new(), room: 3});
+        assert!(w.read_buf.capacity() == 0 && w.write_buf.capacity() == 0);
+        w.write_buf(b"hello").unwrap();
+        assert_eq!(w.flush().unwrap_err().kind(), stdio::ErrorKind::BrokenPipe);
+        w.get_mut().room = 10;
+        assert_eq!(w.into_inner().unwrap().out, b"hello");
+    }
+}
@@ -11,6 +11,25 @@ pub mod const_list;
 pub mod string;
 pub mod io_wrap;
 pub mod magic_bytes;
+pub mod error;
+pub mod incremental;
+pub mod frame;
+pub mod log;
+pub mod container;
+pub mod byte_length;
+pub mod block_list;
+pub mod runtime;
+pub mod envelope;
+pub mod descriptor;
+pub mod dynamic;
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+pub use error::{Error, ErrorKind};
 
 //mod cursed;
 pub fn assert_serde_across_through<Ein, Eout, Tin, Tout>(item: &Tin)
@@ -1,7 +1,9 @@
 use std::marker::PhantomData;
 
 use crate::io;
-use crate::serde::{ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
+use crate::runtime::{Decode, DynLengthPrefixList, Encode};
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+use crate::serde::{ByteConstTypeId, ByteTypeId, ByteConstSize, ByteDeserialize, ByteSerialize, ParseOrIOError};
 
 #[derive(Default, Debug, Clone, Copy)]
 pub struct LengthPrefixList<LE, VE>
@@ -16,6 +18,36 @@ where
 pub enum ListParseError<L, V> {
     LengthParseError(L),
     ItemParseError{idx: u64, error: V},
+    /// The remaining items of a list of `len` constant-size items take up more bytes than fit in a `u64`
+    TooLong{len: u64},
+}
+
+impl<L, V> std::fmt::Display for ListParseError<L, V>
+where
+    L: std::fmt::Display,
+    V: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LengthParseError(e) => write!(f, "invalid list length: {}", e),
+            Self::ItemParseError{idx, error} => write!(f, "invalid list item at index {}: {}", idx, error),
+            Self::TooLong{len} => write!(f, "list of {} items is too long", len),
+        }
+    }
+}
+
+impl<L, V> std::error::Error for ListParseError<L, V>
+where
+    L: std::error::Error + 'static,
+    V: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LengthParseError(e) => Some(e),
+            Self::ItemParseError{error, ..} => Some(error),
+            Self::TooLong{..} => None,
+        }
+    }
 }
 
 impl<L, V> From<L> for ListParseError<L, V> {
@@ -40,6 +72,27 @@ macro_rules! impl_byte_type {
                 res.push(">");
                 res
             }
+
+            fn type_descriptor() -> TypeDescriptor {
+                TypeDescriptor::new(TypeKind::List, "twee::LengthPrefixedList", vec![
+                    TypeParam::Type(LE::type_descriptor()),
+                    TypeParam::Type(VE::type_descriptor()),
+                ])
+            }
+        }
+
+        impl<LE, VE, $v> ByteConstTypeId<$t> for LengthPrefixList<LE, VE>
+        where
+            LE: ByteConstTypeId<u64>,
+            VE: ByteConstTypeId<$v>,
+        {
+            const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+                ConstTypeId::Str("twee::LengthPrefixedList<"),
+                LE::TYPE_ID,
+                ConstTypeId::Str(","),
+                VE::TYPE_ID,
+                ConstTypeId::Str(">"),
+            ]);
         }
     }
 }
@@ -47,6 +100,12 @@ macro_rules! impl_byte_type {
 impl_byte_type!{V, Vec<V>}
 impl_byte_type!{V, [V]}
 
+/// How many items to make room for up front in a list that claims to hold `length`, so a corrupt length can't ask for a huge allocation before any item has been read
+pub(crate) fn prealloc_len<V>(length: u64) -> usize {
+    const MAX_PREALLOC_BYTES:usize = 64 * 1024;
+    length.min((MAX_PREALLOC_BYTES / std::mem::size_of::<V>().max(1)) as u64) as usize
+}
+
 impl<LE, VE, V> ByteDeserialize<Vec<V>> for LengthPrefixList<LE, VE>
 where
     LE: ByteDeserialize<u64>,
@@ -55,14 +114,52 @@ where
     type ParseErr = ListParseError<LE::ParseErr, VE::ParseErr>;
 
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<Vec<V>, ParseOrIOError<Self::ParseErr, R::Err>> {
-        let length:u64 = LE::byte_deserialize(io).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+        DynLengthPrefixList::new(LE::default(), VE::default()).decode(io)
+    }
 
-        let mut res = Vec::with_capacity(length.try_into().unwrap());
+    /// Decodes into the items already in `place` before pushing new ones. On failure, `place` is cut down to the items decoded before the error.
+    fn byte_deserialize_in_place<R: io::ByteRead>(io: &mut R, place: &mut Vec<V>) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let length:u64 = match LE::byte_deserialize(io) {
+            Ok(length) => length,
+            Err(e) => {
+                place.clear();
+                return Err(e.map_parse(ListParseError::LengthParseError));
+            },
+        };
+        let length_us:usize = length.try_into().unwrap();
+        place.truncate(length_us);
+
+        for idx in 0..length {
+            let idx_us = idx as usize;
+            let res = if idx_us < place.len() {
+                VE::byte_deserialize_in_place(io, &mut place[idx_us])
+            } else {
+                VE::byte_deserialize(io).map(|v| place.push(v))
+            };
+            if let Err(e) = res {
+                place.truncate(idx_us);
+                return Err(e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}));
+            }
+        }
+        Ok(())
+    }
+
+    /// Items of a constant size are skipped all at once.
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let Some(size) = VE::guess_size() else {
+            return Decode::<Vec<V>>::skip(&DynLengthPrefixList::new(LE::default(), VE::default()), io);
+        };
+        let length:u64 = LE::byte_deserialize(io).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+        let total = length.checked_mul(size as u64).ok_or(ParseOrIOError::Parse(ListParseError::TooLong{len: length}))?;
+        io.skip_buf(total).map_err(ParseOrIOError::IO)
+    }
 
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let length:u64 = LE::byte_deserialize(io).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
         for idx in 0..length {
-            res.push(VE::byte_deserialize(io).map_err(|e| e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}))?);
+            VE::byte_validate(io).map_err(|e| e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}))?;
         }
-        Ok(res)
+        Ok(())
     }
 }
 
@@ -72,18 +169,169 @@ where
     VE: ByteSerialize<V>,
 { 
     fn byte_serialize<W: io::ByteWrite>(item: &[V], io: &mut W) -> Result<(), W::Err> {
+        DynLengthPrefixList::new(LE::default(), VE::default()).encode(item, io)
+    }
+
+    fn byte_serialize_seekable<W: io::SeekableByteWrite>(item: &[V], io: &mut W) -> Result<(), W::Err> {
         let length:u64 = item.len().try_into().unwrap();
-        LE::byte_serialize(&length, io)?;
+        LE::byte_serialize_seekable(&length, io)?;
 
         for el in item.iter() {
-            VE::byte_serialize(&el, io)?;
+            VE::byte_serialize_seekable(el, io)?;
         }
         Ok(())
     }
 
     fn size(item: &[V]) -> u64 {
-        let length:u64 = item.len().try_into().unwrap();
-        LE::size(&length) + item.iter().map(VE::size).sum::<u64>()
+        DynLengthPrefixList::new(LE::default(), VE::default()).encoded_size(item)
+    }
+}
+
+impl<LE, VE, V> ByteSerialize<Vec<V>> for LengthPrefixList<LE, VE>
+where
+    LE: ByteSerialize<u64>,
+    VE: ByteSerialize<V>,
+{
+    fn byte_serialize<W: io::ByteWrite>(item: &Vec<V>, io: &mut W) -> Result<(), W::Err> {
+        <Self as ByteSerialize<[V]>>::byte_serialize(item, io)
+    }
+
+    fn byte_serialize_seekable<W: io::SeekableByteWrite>(item: &Vec<V>, io: &mut W) -> Result<(), W::Err> {
+        <Self as ByteSerialize<[V]>>::byte_serialize_seekable(item, io)
+    }
+
+    fn size(item: &Vec<V>) -> u64 {
+        <Self as ByteSerialize<[V]>>::size(item)
+    }
+}
+
+type ListResult<T, LE, VE, V, R> = Result<T, ParseOrIOError<ListParseError<<LE as ByteDeserialize<u64>>::ParseErr, <VE as ByteDeserialize<V>>::ParseErr>, <R as io::ByteRead>::Err>>;
+
+impl<LE, VE> LengthPrefixList<LE, VE>
+where
+    LE: ByteDeserialize<u64>,
+{
+    /// Reads the length prefix, then returns an iterator that decodes the items one at a time instead of collecting them into a `Vec`.
+    pub fn iter<V, R: io::ByteRead>(io: &mut R) -> ListResult<ListIter<'_, LE, VE, V, R>, LE, VE, V, R>
+    where
+        VE: ByteDeserialize<V>,
+    {
+        let len = LE::byte_deserialize(io).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+        Ok(ListIter{
+            io,
+            len,
+            idx: 0,
+            failed: false,
+            length_encoder: PhantomData,
+            value_encoder: PhantomData,
+            value: PhantomData,
+        })
+    }
+}
+
+/// Lazily decodes the items of a [`LengthPrefixList`].
+///
+/// Any items that haven't been read are skipped on drop with [`ByteDeserialize::byte_skip`], ignoring errors, so the reader ends up after the list either way. Use [`ListIter::finish`] to see those errors, or [`ListIter::skip_remaining`] to skip constant-size items in one go. After an error, iteration ends and the reader is left where the error happened.
+#[derive(Debug)]
+pub struct ListIter<'r, LE, VE, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    R: io::ByteRead,
+{
+    io: &'r mut R,
+    len: u64,
+    idx: u64,
+    failed: bool,
+    length_encoder: PhantomData<LE>,
+    value_encoder: PhantomData<VE>,
+    value: PhantomData<fn() -> V>,
+}
+
+impl<'r, LE, VE, V, R> ListIter<'r, LE, VE, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    R: io::ByteRead,
+{
+    /// The length of the whole list
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many items haven't been read yet
+    pub fn remaining(&self) -> u64 {
+        if self.failed { 0 } else { self.len - self.idx }
+    }
+
+    /// Skips any remaining items, returning the first error.
+    pub fn finish(mut self) -> ListResult<(), LE, VE, V, R> {
+        self.skip_each()
+    }
+
+    fn skip_each(&mut self) -> ListResult<(), LE, VE, V, R> {
+        while self.remaining() > 0 {
+            let idx = self.idx;
+            let res = VE::byte_skip(self.io).map_err(|e| e.map_parse(|error| ListParseError::ItemParseError{idx, error}));
+            self.idx += 1;
+            self.failed = res.is_err();
+            res?;
+        }
+        Ok(())
+    }
+
+    /// Skips the remaining items all at once
+    pub fn skip_remaining(&mut self) -> ListResult<(), LE, VE, V, R>
+    where
+        VE: ByteConstSize<V>,
+    {
+        let Some(size) = self.remaining().checked_mul(VE::BYTE_SIZE as u64) else {
+            self.failed = true;
+            return Err(ParseOrIOError::Parse(ListParseError::TooLong{len: self.len}));
+        };
+        self.io.skip_buf(size).map_err(ParseOrIOError::IO)?;
+        self.idx = self.len;
+        Ok(())
+    }
+}
+
+impl<'r, LE, VE, V, R> Iterator for ListIter<'r, LE, VE, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    R: io::ByteRead,
+{
+    type Item = ListResult<V, LE, VE, V, R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining() == 0 {
+            return None;
+        }
+        let idx = self.idx;
+        let res = VE::byte_deserialize(self.io).map_err(|e| e.map_parse(|error| ListParseError::ItemParseError{idx, error}));
+        self.idx += 1;
+        self.failed = res.is_err();
+        Some(res)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining().try_into().unwrap_or(usize::MAX);
+        (0, Some(remaining))
+    }
+}
+
+impl<'r, LE, VE, V, R> Drop for ListIter<'r, LE, VE, V, R>
+where
+    LE: ByteDeserialize<u64>,
+    VE: ByteDeserialize<V>,
+    R: io::ByteRead,
+{
+    fn drop(&mut self) {
+        let _ = self.skip_each();
     }
 }
 
@@ -100,4 +348,109 @@ mod test {
             Vec<_>,
         >(a.as_slice());
     }
+
+    #[test]
+    fn lazy() {
+        use crate::endians::NumberEncodingBE as BE;
+        use crate::varint::UVarInt;
+        use crate::string::LengthPrefixString;
+        type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+        let strings:Vec<String> = ["a", "bb", "ccc", "dddd"].map(String::from).into();
+        let mut data = Vec::new();
+        Strings::byte_serialize(&strings, &mut data).unwrap();
+        data.push(0xaa);
+
+        // stopping early still leaves the reader after the list
+        let mut cur = io::ByteCursor::new(&data);
+        let found = Strings::iter(&mut cur).unwrap().map(Result::unwrap).find(|s:&String| s.len() == 2);
+        assert_eq!(found.as_deref(), Some("bb"));
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+
+        let mut cur = io::ByteCursor::new(&data);
+        let mut it = Strings::iter::<String, _>(&mut cur).unwrap();
+        assert_eq!(it.len(), 4);
+        it.next();
+        assert_eq!(it.remaining(), 3);
+        it.finish().unwrap();
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+
+        let mut nums = Vec::new();
+        LengthPrefixList::<UVarInt, BE>::byte_serialize(&vec![1u32, 2, 3], &mut nums).unwrap();
+        nums.push(0xaa);
+        let mut cur = io::ByteCursor::new(&nums);
+        let mut it = LengthPrefixList::<UVarInt, BE>::iter::<u32, _>(&mut cur).unwrap();
+        assert_eq!(it.next(), Some(Ok(1)));
+        it.skip_remaining().unwrap();
+        assert!(it.next().is_none());
+        drop(it);
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+
+        // a length whose items can't all fit in a u64 of bytes
+        let mut huge = Vec::new();
+        UVarInt::byte_serialize(&u64::MAX, &mut huge).unwrap();
+        let mut cur = io::ByteCursor::new(&huge);
+        let mut it = LengthPrefixList::<UVarInt, BE>::iter::<u32, _>(&mut cur).unwrap();
+        assert_eq!(it.skip_remaining(), Err(ParseOrIOError::Parse(ListParseError::TooLong{len: u64::MAX})));
+        assert!(it.next().is_none());
+
+        // an item that fails to parse ends iteration
+        let bad = [2, 1, 0xff, 0];
+        let mut cur = io::ByteCursor::new(&bad);
+        let mut it = Strings::iter::<String, _>(&mut cur).unwrap();
+        assert!(matches!(it.next(), Some(Err(ParseOrIOError::Parse(ListParseError::ItemParseError{idx: 0, ..})))));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn skip_const_size() {
+        use crate::varint::UVarInt;
+        use crate::magic_bytes::magic_bytes_type;
+
+        magic_bytes_type!{
+            struct Tag[b'x'];
+        }
+        type Tags = LengthPrefixList<UVarInt, Tag>;
+
+        // the items are skipped over by size, so they aren't checked
+        let data = [3, b'a', b'b', b'c', 0xaa];
+        let mut cur = io::ByteCursor::new(&data);
+        Tags::byte_skip(&mut cur).unwrap();
+        assert_eq!(cur.position(), 4);
+        assert!(<Tags as ByteDeserialize<Vec<()>>>::byte_deserialize(&mut io::ByteCursor::new(&data)).is_err());
+
+        let mut cur = io::ByteCursor::new(&data[..3]);
+        assert!(matches!(Tags::byte_skip(&mut cur), Err(ParseOrIOError::IO(_))));
+
+        let mut huge = Vec::new();
+        UVarInt::byte_serialize(&u64::MAX, &mut huge).unwrap();
+        let res = <LengthPrefixList<UVarInt, crate::endians::NumberEncodingBE> as ByteDeserialize<Vec<u32>>>::byte_skip(&mut io::ByteCursor::new(&huge));
+        assert!(matches!(res, Err(ParseOrIOError::Parse(ListParseError::TooLong{len: u64::MAX}))));
+    }
+
+    #[test]
+    fn in_place() {
+        use crate::varint::UVarInt;
+        use crate::string::LengthPrefixString;
+        type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+        let mut data = Vec::new();
+        Strings::byte_serialize(&vec!["abc".to_string(), "de".to_string()], &mut data).unwrap();
+        let mut place = vec!["x".repeat(10), "y".repeat(10), "z".repeat(10)];
+        let ptr = place[0].as_ptr();
+        Strings::byte_deserialize_in_place(&mut io::ByteCursor::new(&data), &mut place).unwrap();
+        assert_eq!(place, ["abc", "de"]);
+        assert_eq!(place[0].as_ptr(), ptr);
+
+        let mut data = Vec::new();
+        Strings::byte_serialize(&vec!["f".to_string(); 3], &mut data).unwrap();
+        Strings::byte_deserialize_in_place(&mut io::ByteCursor::new(&data), &mut place).unwrap();
+        assert_eq!(place, ["f", "f", "f"]);
+
+        // a bad item leaves the ones before it
+        let bad = [3, 1, b'g', 1, 0xff, 0];
+        let res = Strings::byte_deserialize_in_place(&mut io::ByteCursor::new(&bad), &mut place);
+        assert!(matches!(res, Err(ParseOrIOError::Parse(ListParseError::ItemParseError{idx: 1, ..}))));
+        assert_eq!(place, ["g"]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,492 @@
+use std::fmt;
+use std::fs::File;
+use std::io as stdio;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use crate::endians::NumberEncodingLE;
+use crate::io::{ByteCursor, ByteRead};
+use crate::io_wrap::BufWrapper;
+use crate::magic_bytes::{magic_bytes_type, MagicMismatch};
+use crate::serde::{ByteConstSize, ByteDeserialize, ByteSerialize, ParseOrIOError};
+
+magic_bytes_type!{
+    /// The first bytes of every log file
+    pub struct LogMagic[b'T', b'W', b'L', 0x01];
+}
+
+const HEADER_LEN:usize = <LogMagic as ByteConstSize<()>>::BYTE_SIZE;
+/// Each record starts with its payload length and the CRC32 of that length followed by the payload, both `u32` little-endian.
+///
+/// Covering the length means a zero-filled header, as left by a crash after a file was extended, never passes for an empty record.
+const RECORD_HEADER_LEN:usize = 8;
+
+/// Records longer than this are assumed to be corruption unless [`LogReader::with_max_record_size`] says otherwise
+pub const DEFAULT_MAX_RECORD_SIZE:u32 = 16 << 20;
+
+const CRC_TABLE:[u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 as used by zip and png
+fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continues `crc`, the CRC-32 of some data, so that it also covers `data` coming after it
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for b in data {
+        c = CRC_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// Whether everything from `pos` to the end of `inner` is zero bytes
+fn zero_filled<R: Read + Seek>(inner: &mut R, pos: u64) -> stdio::Result<bool> {
+    inner.seek(SeekFrom::Start(pos))?;
+    let mut buf = [0; 4096];
+    loop {
+        match inner.read(&mut buf) {
+            Ok(0) => return Ok(true),
+            Ok(n) if buf[..n].iter().any(|b| *b != 0) => return Ok(false),
+            Ok(_) => (),
+            Err(e) if e.kind() == stdio::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Files that can be cut short, so that recovery can drop a torn tail.
+pub trait Truncate {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()>;
+}
+
+impl Truncate for File {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()> {
+        File::set_len(self, len)
+    }
+}
+
+impl Truncate for &File {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()> {
+        File::set_len(self, len)
+    }
+}
+
+impl Truncate for stdio::Cursor<Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()> {
+        self.get_mut().truncate(len.try_into().unwrap());
+        Ok(())
+    }
+}
+
+impl Truncate for stdio::Cursor<&mut Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()> {
+        self.get_mut().truncate(len.try_into().unwrap());
+        Ok(())
+    }
+}
+
+impl<T: Truncate + ?Sized> Truncate for &mut T {
+    fn set_len(&mut self, len: u64) -> stdio::Result<()> {
+        (**self).set_len(len)
+    }
+}
+
+#[derive(Debug)]
+pub enum LogError<P> {
+    /// The file doesn't start with [`LogMagic`]
+    NotALog(MagicMismatch<HEADER_LEN>),
+    /// The record at `offset` has a bad checksum or an absurd length. Nothing after it can be trusted.
+    Corrupt{offset: u64},
+    /// The record at `offset` is intact but its payload couldn't be parsed. Reading can continue with the next record.
+    Payload{offset: u64, error: P},
+    /// The payload of the record at `offset` didn't fill the record exactly. Reading can continue with the next record.
+    Malformed{offset: u64},
+    IO(stdio::Error),
+}
+
+impl<P: fmt::Display> fmt::Display for LogError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotALog(e) => write!(f, "not a log file: {}", e),
+            Self::Corrupt{offset} => write!(f, "corrupt record at offset {}", offset),
+            Self::Payload{offset, error} => write!(f, "invalid record at offset {}: {}", offset, error),
+            Self::Malformed{offset} => write!(f, "record at offset {} doesn't match its length", offset),
+            Self::IO(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl<P: std::error::Error + 'static> std::error::Error for LogError<P> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NotALog(e) => Some(e),
+            Self::Payload{error, ..} => Some(error),
+            Self::IO(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl<P> From<stdio::Error> for LogError<P> {
+    fn from(e: stdio::Error) -> Self {
+        Self::IO(e)
+    }
+}
+
+/// Appends records encoded with `E` to a log.
+///
+/// Each record goes out in a single write, so a crash leaves at most one torn record at the end.
+#[derive(Debug)]
+pub struct LogWriter<E, W> {
+    inner: W,
+    encoder: PhantomData<E>,
+}
+
+impl<E, W: Write> LogWriter<E, W> {
+    /// Starts a new log, writing the header to `inner`
+    pub fn create(mut inner: W) -> stdio::Result<Self> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        LogMagic::byte_serialize(&(), &mut header).unwrap();
+        inner.write_all(&header)?;
+        Ok(Self::append_to(inner))
+    }
+
+    /// Continues a log whose header and records have already been written, appending at the current position of `inner`
+    pub fn append_to(inner: W) -> Self {
+        Self{
+            inner,
+            encoder: PhantomData,
+        }
+    }
+
+    pub fn append<T: ?Sized>(&mut self, item: &T) -> stdio::Result<()>
+    where
+        E: ByteSerialize<T>,
+    {
+        let size = E::size(item);
+        let len:u32 = size.try_into().map_err(|_| stdio::Error::new(
+            stdio::ErrorKind::InvalidInput,
+            format!("record of {} bytes is too large for a log", size),
+        ))?;
+        let mut buf = Vec::with_capacity(RECORD_HEADER_LEN + len as usize);
+        buf.resize(RECORD_HEADER_LEN, 0);
+        E::byte_serialize(item, &mut buf).unwrap();
+        buf[..4].copy_from_slice(&len.to_le_bytes());
+        let crc = crc32_update(crc32(&buf[..4]), &buf[RECORD_HEADER_LEN..]);
+        buf[4..RECORD_HEADER_LEN].copy_from_slice(&crc.to_le_bytes());
+        self.inner.write_all(&buf)
+    }
+
+    pub fn flush(&mut self) -> stdio::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads the records of a log written by [`LogWriter`].
+///
+/// Iteration stops quietly at a torn record at the end of the file, and stops after yielding [`LogError::Corrupt`] at a record whose checksum doesn't match. Either way, [`LogReader::valid_len`] is where the intact part of the log ends, though only a torn or corrupt *last* record is cut off by [`LogReader::recover`].
+#[derive(Debug)]
+pub struct LogReader<E, T, R> {
+    inner: BufWrapper<R>,
+    valid_len: u64,
+    max_record_size: u32,
+    torn: bool,
+    /// Where the corrupt record that stopped reading claims to end
+    corrupt_end: Option<u64>,
+    done: bool,
+    encoder: PhantomData<E>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<E, T, R: Read> LogReader<E, T, R> {
+    /// Checks the header. A file too short to hold all of the header counts as an empty log with a torn tail.
+    pub fn open(inner: R) -> Result<Self, LogError<E::ParseErr>>
+    where
+        E: ByteDeserialize<T>,
+    {
+        let mut inner = BufWrapper::new(inner);
+        let header = inner.read_up_to(HEADER_LEN as u64)?;
+        let mut expected = Vec::with_capacity(HEADER_LEN);
+        LogMagic::byte_serialize(&(), &mut expected).unwrap();
+        let (torn, valid_len) = match LogMagic::byte_deserialize(&mut ByteCursor::new(&header)) {
+            Ok(()) => (false, HEADER_LEN as u64),
+            Err(ParseOrIOError::Parse(e)) => return Err(LogError::NotALog(e)),
+            Err(ParseOrIOError::IO(_)) if expected.starts_with(&header) => (!header.is_empty(), 0),
+            Err(ParseOrIOError::IO(_)) => {
+                let mut found = [0; HEADER_LEN];
+                found[..header.len()].copy_from_slice(&header);
+                return Err(LogError::NotALog(MagicMismatch{expected: expected.try_into().unwrap(), found, candidates: LogMagic::PATTERNS}));
+            },
+        };
+        Ok(Self{
+            inner,
+            valid_len,
+            max_record_size: DEFAULT_MAX_RECORD_SIZE,
+            torn,
+            corrupt_end: None,
+            done: valid_len == 0,
+            encoder: PhantomData,
+            value: PhantomData,
+        })
+    }
+
+    /// Treats records longer than `max` bytes as corruption, rather than trying to read them in.
+    pub fn with_max_record_size(mut self, max: u32) -> Self {
+        self.max_record_size = max;
+        self
+    }
+
+    /// The length of the intact part of the log read so far
+    pub fn valid_len(&self) -> u64 {
+        self.valid_len
+    }
+
+    /// Whether reading stopped at a torn or corrupt record rather than at the end of the file
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.inner.get_ref()
+    }
+
+    pub fn into_inner(self) -> stdio::Result<R> {
+        Ok(self.inner.into_parts().0)
+    }
+}
+
+impl<E, T, R> LogReader<E, T, R>
+where
+    E: ByteDeserialize<T>,
+    R: Read,
+{
+    fn read_record(&mut self) -> Option<<Self as Iterator>::Item> {
+        let offset = self.valid_len;
+        let header = match self.inner.read_up_to(RECORD_HEADER_LEN as u64) {
+            Ok(buf) if buf.is_empty() => return None,
+            Ok(buf) if buf.len() < RECORD_HEADER_LEN => {
+                self.torn = true;
+                return None;
+            },
+            Ok(buf) => buf.into_owned(),
+            Err(e) => return Some(Err(LogError::IO(e))),
+        };
+        let mut cur = ByteCursor::new(&header);
+        let len:u32 = NumberEncodingLE::byte_deserialize(&mut cur).unwrap();
+        let crc:u32 = NumberEncodingLE::byte_deserialize(&mut cur).unwrap();
+        if len > self.max_record_size {
+            self.torn = true;
+            self.corrupt_end = Some(offset + (RECORD_HEADER_LEN as u64) + len as u64);
+            return Some(Err(LogError::Corrupt{offset}));
+        }
+        let payload = match self.inner.read_up_to(len as u64) {
+            Ok(buf) if buf.len() < len as usize => {
+                self.torn = true;
+                return None;
+            },
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(LogError::IO(e))),
+        };
+        if crc32_update(crc32(&header[..4]), &payload) != crc {
+            self.torn = true;
+            self.corrupt_end = Some(offset + (RECORD_HEADER_LEN as u64) + len as u64);
+            return Some(Err(LogError::Corrupt{offset}));
+        }
+        let mut cur = ByteCursor::new(&payload);
+        let res = match E::byte_deserialize(&mut cur) {
+            Ok(v) if cur.at_end() => Ok(v),
+            Ok(_) | Err(ParseOrIOError::IO(_)) => Err(LogError::Malformed{offset}),
+            Err(ParseOrIOError::Parse(error)) => Err(LogError::Payload{offset, error}),
+        };
+        self.valid_len += (RECORD_HEADER_LEN as u64) + len as u64;
+        Some(res)
+    }
+
+    /// Reads to the end of the log, cuts off a torn or corrupt last record, and returns a writer that appends after the last intact one.
+    ///
+    /// A corrupt record with anything but zero bytes after it isn't a torn tail, so rather than dropping everything that follows it, this fails with [`std::io::ErrorKind::InvalidData`] and leaves the file alone. Records that are intact but fail to parse are kept.
+    pub fn recover(mut self) -> stdio::Result<LogWriter<E, R>>
+    where
+        R: Write + Seek + Truncate,
+    {
+        for res in self.by_ref() {
+            if let Err(LogError::IO(e)) = res {
+                return Err(e);
+            }
+        }
+        let valid_len = self.valid_len;
+        if let Some(corrupt_end) = self.corrupt_end {
+            let file_len = self.inner.get_mut().seek(SeekFrom::End(0))?;
+            if corrupt_end != file_len && !zero_filled(self.inner.get_mut(), valid_len)? {
+                return Err(stdio::Error::new(
+                    stdio::ErrorKind::InvalidData,
+                    format!("corrupt record at offset {} isn't at the end of the log", valid_len),
+                ));
+            }
+        }
+        let mut inner = self.inner.into_inner()?;
+        inner.set_len(valid_len)?;
+        inner.seek(SeekFrom::Start(valid_len))?;
+        if valid_len == 0 {
+            LogWriter::create(inner)
+        } else {
+            Ok(LogWriter::append_to(inner))
+        }
+    }
+}
+
+impl<E, T, R> Iterator for LogReader<E, T, R>
+where
+    E: ByteDeserialize<T>,
+    R: Read,
+{
+    type Item = Result<T, LogError<E::ParseErr>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.read_record();
+        if !matches!(res, Some(Ok(_)) | Some(Err(LogError::Payload{..} | LogError::Malformed{..}))) {
+            self.done = true;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use crate::varint::UVarInt;
+    use crate::string::LengthPrefixString;
+
+    type Str = LengthPrefixString<UVarInt>;
+    type Reader<'a> = LogReader<Str, String, Cursor<&'a mut Vec<u8>>>;
+
+    #[test]
+    fn blarg() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+
+        let mut w = LogWriter::<Str, _>::create(Vec::new()).unwrap();
+        w.append("hello").unwrap();
+        w.append("").unwrap();
+        let mut data = w.into_inner();
+
+        let mut r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert_eq!(r.by_ref().map(Result::unwrap).collect::<Vec<_>>(), ["hello", ""]);
+        assert!(!r.is_torn());
+
+        r.recover().unwrap().append("world").unwrap();
+        let r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert_eq!(r.map(Result::unwrap).collect::<Vec<_>>(), ["hello", "", "world"]);
+
+        // a flipped bit is caught by the checksum, and recovery drops the damaged record
+        let last = data.len() - 1;
+        data[last] ^= 0x20;
+        let mut r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert_eq!(r.next().unwrap().unwrap(), "hello");
+        assert_eq!(r.next().unwrap().unwrap(), "");
+        assert!(matches!(r.next(), Some(Err(LogError::Corrupt{..}))));
+        assert!(r.next().is_none());
+        r.recover().unwrap();
+        assert_eq!(Reader::open(Cursor::new(&mut data)).unwrap().count(), 2);
+
+        // but damage in the middle isn't a torn tail, so recovery refuses to drop the records after it
+        data[HEADER_LEN + RECORD_HEADER_LEN] ^= 0x20;
+        let before = data.clone();
+        let r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert!(matches!(r.recover(), Err(e) if e.kind() == stdio::ErrorKind::InvalidData));
+        assert_eq!(data, before);
+
+        let mut not_a_log = b"TWX\x01".to_vec();
+        assert!(matches!(Reader::open(Cursor::new(&mut not_a_log)), Err(LogError::NotALog(_))));
+        let mut not_a_log = b"X".to_vec();
+        assert!(matches!(Reader::open(Cursor::new(&mut not_a_log)), Err(LogError::NotALog(_))));
+    }
+
+    #[test]
+    fn zero_padding() {
+        let mut w = LogWriter::<Str, _>::create(Vec::new()).unwrap();
+        w.append("hello").unwrap();
+        w.append("").unwrap();
+        let mut data = w.into_inner();
+        let end = data.len();
+        data.resize(end + 100, 0);
+
+        // the zeroes don't read as empty records
+        let mut r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert_eq!(r.next().unwrap().unwrap(), "hello");
+        assert_eq!(r.next().unwrap().unwrap(), "");
+        assert!(matches!(r.next(), Some(Err(LogError::Corrupt{offset})) if offset == end as u64));
+        assert!(r.is_torn());
+
+        // and recovery cuts them off as a torn tail
+        r.recover().unwrap().append("world").unwrap();
+        assert_eq!(data.len(), end + RECORD_HEADER_LEN + 6);
+        let r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert_eq!(r.map(Result::unwrap).collect::<Vec<_>>(), ["hello", "", "world"]);
+
+        // unless there's something other than zeroes after them
+        data.resize(data.len() + 100, 0);
+        data.push(1);
+        let before = data.clone();
+        let r = Reader::open(Cursor::new(&mut data)).unwrap();
+        assert!(matches!(r.recover(), Err(e) if e.kind() == stdio::ErrorKind::InvalidData));
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn truncation() {
+        let long = "x".repeat(300);
+        let strings = ["one", "", "three", long.as_str()];
+        let mut w = LogWriter::<Str, _>::create(Vec::new()).unwrap();
+        let mut boundaries = vec![w.get_ref().len()];
+        for s in strings {
+            w.append(s).unwrap();
+            boundaries.push(w.get_ref().len());
+        }
+        let full = w.into_inner();
+
+        for cut in 0..full.len() {
+            let mut data = full[..cut].to_vec();
+            let complete = boundaries.iter().filter(|b| **b <= cut).count().saturating_sub(1);
+            let mut r = Reader::open(Cursor::new(&mut data)).unwrap();
+            let read:Vec<String> = r.by_ref().map(Result::unwrap).collect();
+            assert_eq!(read, strings[..complete], "cut at {}", cut);
+            assert_eq!(r.is_torn(), cut != 0 && !boundaries.contains(&cut), "cut at {}", cut);
+
+            r.recover().unwrap().append("after").unwrap();
+            let read:Vec<String> = Reader::open(Cursor::new(&mut data)).unwrap().map(Result::unwrap).collect();
+            assert_eq!(read.len(), complete + 1, "cut at {}", cut);
+            assert_eq!(read[complete], "after", "cut at {}", cut);
+        }
+    }
+}
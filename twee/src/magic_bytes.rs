@@ -1,19 +1,99 @@
 use std::fmt;
 
+/// Magic bytes to match against. Only the bits set in `mask` have to match, so a zero mask byte is a "don't care".
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MagicPattern<const N:usize> {
+    pub bytes:[u8; N],
+    pub mask:[u8; N],
+}
+
+impl<const N:usize> MagicPattern<N> {
+    pub const fn exact(bytes: [u8; N]) -> Self {
+        Self{bytes, mask: [0xff; N]}
+    }
+
+    pub const fn masked(bytes: [u8; N], mask: [u8; N]) -> Self {
+        Self{bytes, mask}
+    }
+
+    pub const fn matches(&self, found: &[u8; N]) -> bool {
+        let mut i = 0;
+        while i < N {
+            if found[i] & self.mask[i] != self.bytes[i] & self.mask[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+}
+
+/// Written like a byte array, with `_` for bytes that are entirely masked out and `byte&mask` for those that partly are
+impl<const N:usize> fmt::Display for MagicPattern<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (i, (b, m)) in self.bytes.iter().zip(self.mask.iter()).enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            match m {
+                0xff => write!(f, "{}", b)?,
+                0 => write!(f, "_")?,
+                m => write!(f, "{}&{:#04x}", b & m, m)?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// Returned by [`magic_bytes_type!`] and [`magic_enum!`] encoders when no magic matches
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct MagicMismatch<const N:usize> {
+    /// The magic that is written when encoding, or for a [`magic_enum!`], the one written for its first variant
     pub expected:[u8; N],
     pub found:[u8; N],
+    /// Every pattern that would have been accepted
+    pub candidates:&'static [MagicPattern<N>],
 }
 
 impl<const N:usize> fmt::Display for MagicMismatch<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "unexpected magic bytes {:?}, expecting {:?}", self.found, self.expected)
+        write!(f, "unexpected magic bytes {:?}, expecting ", self.found)?;
+        if self.candidates.len() <= 1 {
+            return write!(f, "{:?}", self.expected);
+        }
+        write!(f, "one of ")?;
+        for (i, pattern) in self.candidates.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            pattern.fmt(f)?;
+        }
+        Ok(())
     }
 }
 
 impl<const N:usize> std::error::Error for MagicMismatch<N> {}
 
+/// Returned by the `little`/`big` form of [`magic_bytes_type!`] when neither magic matches
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EndianMagicMismatch<const N:usize> {
+    pub little:[u8; N],
+    pub big:[u8; N],
+    pub found:[u8; N],
+}
+
+impl<const N:usize> fmt::Display for EndianMagicMismatch<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unexpected magic bytes {:?}, expecting {:?} (little endian) or {:?} (big endian)", self.found, self.little, self.big)
+    }
+}
+
+impl<const N:usize> std::error::Error for EndianMagicMismatch<N> {}
+
+/// Defines unit structs that encode `()` as a fixed byte sequence, failing to decode anything else.
+///
+/// The second form instead encodes an [`Endianness`][crate::endians::Endianness] as one of two byte sequences of the same length, like TIFF's `II` and `MM`.
 #[macro_export]
 macro_rules! magic_bytes_type {
     (
@@ -27,16 +107,33 @@ macro_rules! magic_bytes_type {
             $(#[$struct_meta])*
             $sv struct $name;
 
+            #[allow(dead_code)]
+            impl $name {
+                pub const PATTERNS: &'static [$crate::magic_bytes::MagicPattern<{0 $( + 1 + ($n - $n) as usize)*}>] = &[
+                    $crate::magic_bytes::MagicPattern::exact([$($n,)*]),
+                ];
+            }
+
             impl $crate::serde::ByteTypeId<()> for $name {
                 fn byte_type_id() -> ::std::vec::Vec<&'static str> {
-                    vec![
-                        concat!(
-                            "MagicBytes<", $( stringify!($n), ",", )* ">"
-                        )
-                    ]
+                    <Self as $crate::serde::ByteConstTypeId<()>>::TYPE_ID.pieces()
+                }
+
+                fn type_descriptor() -> $crate::descriptor::TypeDescriptor {
+                    $crate::descriptor::TypeDescriptor::new(
+                        $crate::descriptor::TypeKind::Magic,
+                        "MagicBytes",
+                        vec![$crate::descriptor::TypeParam::Value(concat!($( stringify!($n), ",", )*).into())],
+                    ).with_fixed_size(0 $( + 1 + ($n - $n) as u64)*)
                 }
             }
             
+            impl $crate::serde::ByteConstTypeId<()> for $name {
+                const TYPE_ID: $crate::descriptor::ConstTypeId = $crate::descriptor::ConstTypeId::Str(concat!(
+                    "MagicBytes<", $( stringify!($n), ",", )* ">"
+                ));
+            }
+
             impl $crate::serde::ByteConstSize<()> for $name {
                 const BYTE_SIZE:usize = 0 $( + 1 + ($n - $n) as usize)*;
             }
@@ -51,6 +148,7 @@ macro_rules! magic_bytes_type {
                         ::std::result::Result::Err($crate::magic_bytes::MagicMismatch{
                             expected: [$($n,)*],
                             found: <[u8; 0 $( + 1 + ($n - $n) as usize)*] as ::std::clone::Clone>::clone(io),
+                            candidates: Self::PATTERNS,
                         })
                     }
                 }
@@ -63,15 +161,213 @@ macro_rules! magic_bytes_type {
             }
         )+
     };
+    (
+        $(
+            $(#[$struct_meta:meta])*
+            $sv:vis struct $name:ident {
+                little: [$($l:literal),*],
+                big: [$($b:literal),*] $(,)?
+            }
+        )+
+    ) => {
+        $(
+            #[derive(Debug, Default, Copy, Clone)]
+            $(#[$struct_meta])*
+            $sv struct $name;
+
+            const _: () = ::std::assert!(
+                (0 $( + 1 + ($l - $l) as usize)*) == (0 $( + 1 + ($b - $b) as usize)*),
+                "little and big endian magics must be the same length",
+            );
+
+            impl $crate::serde::ByteTypeId<$crate::endians::Endianness> for $name {
+                fn byte_type_id() -> ::std::vec::Vec<&'static str> {
+                    <Self as $crate::serde::ByteConstTypeId<$crate::endians::Endianness>>::TYPE_ID.pieces()
+                }
+
+                fn type_descriptor() -> $crate::descriptor::TypeDescriptor {
+                    $crate::descriptor::TypeDescriptor::new(
+                        $crate::descriptor::TypeKind::Magic,
+                        "EndianMagicBytes",
+                        vec![$crate::descriptor::TypeParam::Value(concat!($( stringify!($l), ",", )* ";", $( stringify!($b), ",", )*).into())],
+                    ).with_fixed_size(0 $( + 1 + ($l - $l) as u64)*)
+                }
+            }
+
+            impl $crate::serde::ByteConstTypeId<$crate::endians::Endianness> for $name {
+                const TYPE_ID: $crate::descriptor::ConstTypeId = $crate::descriptor::ConstTypeId::Str(concat!(
+                    "EndianMagicBytes<", $( stringify!($l), ",", )* ";", $( stringify!($b), ",", )* ">"
+                ));
+            }
+
+            impl $crate::serde::ByteConstSize<$crate::endians::Endianness> for $name {
+                const BYTE_SIZE:usize = 0 $( + 1 + ($l - $l) as usize)*;
+            }
+
+            impl $crate::serde::ByteConstDeserialize<$crate::endians::Endianness> for $name {
+                type ParseErr = $crate::magic_bytes::EndianMagicMismatch<{0 $( + 1 + ($l - $l) as usize)*}>;
+
+                fn byte_const_deserialize(io: &[u8; 0 $( + 1 + ($l - $l) as usize)*]) -> ::std::result::Result<$crate::endians::Endianness, Self::ParseErr> {
+                    if io == &[$($l,)*] {
+                        ::std::result::Result::Ok($crate::endians::Endianness::Little)
+                    } else if io == &[$($b,)*] {
+                        ::std::result::Result::Ok($crate::endians::Endianness::Big)
+                    } else {
+                        ::std::result::Result::Err($crate::magic_bytes::EndianMagicMismatch{
+                            little: [$($l,)*],
+                            big: [$($b,)*],
+                            found: <[u8; 0 $( + 1 + ($l - $l) as usize)*] as ::std::clone::Clone>::clone(io),
+                        })
+                    }
+                }
+            }
+
+            impl $crate::serde::ByteConstSerialize<$crate::endians::Endianness> for $name {
+                fn byte_const_serialize(item: &$crate::endians::Endianness, io: &mut [u8; 0 $( + 1 + ($l - $l) as usize)*]) {
+                    *io = match item {
+                        $crate::endians::Endianness::Little => [$($l,)*],
+                        $crate::endians::Endianness::Big => [$($b,)*],
+                    };
+                }
+            }
+        )+
+    };
+}
+
+/// Defines an enum with one variant per accepted magic, and a unit struct that encodes it.
+///
+/// All the magics must be the same length. A magic can be followed by `& [mask]` to only compare some bits; masked-out bytes are still consumed when decoding, and encoding writes the magic as given. The first matching variant wins.
+///
+/// ```
+/// twee::magic_bytes::magic_enum!{
+///     pub enum Compression {
+///         Gzip = [0x1f, 0x8b, 0x08, 0x00] & [0xff, 0xff, 0xff, 0x00],
+///         Zstd = [0x28, 0xb5, 0x2f, 0xfd],
+///     }
+///     pub struct CompressionMagic;
+/// }
+/// ```
+#[macro_export]
+macro_rules! magic_enum {
+    (
+        $(
+            $(#[$enum_meta:meta])*
+            $ev:vis enum $enum:ident {
+                $($var:ident = [$($n:literal),*] $(& [$($m:literal),*])?),+ $(,)?
+            }
+            $(#[$struct_meta:meta])*
+            $sv:vis struct $name:ident;
+        )+
+    ) => {
+        $(
+            #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+            $(#[$enum_meta])*
+            $ev enum $enum {
+                $($var,)+
+            }
+
+            #[derive(Debug, Default, Copy, Clone)]
+            $(#[$struct_meta])*
+            $sv struct $name;
+
+            $crate::magic_enum!(@encoder $name for $enum {
+                $($enum::$var = [$($n),*] $(& [$($m),*])?,)+
+            });
+        )+
+    };
+    (@pattern [$($n:literal),*]) => {
+        $crate::magic_bytes::MagicPattern::exact([$($n,)*])
+    };
+    (@pattern [$($n:literal),*] [$($m:literal),*]) => {
+        $crate::magic_bytes::MagicPattern::masked([$($n,)*], [$($m,)*])
+    };
+    (@type_id [$($n:literal),*]) => {
+        concat!("[", $( stringify!($n), ",", )* "]")
+    };
+    (@type_id [$($n:literal),*] [$($m:literal),*]) => {
+        concat!("[", $( stringify!($n), ",", )* "]&[", $( stringify!($m), ",", )* "]")
+    };
+    (@encoder $name:ident for $t:ty {
+        $fvar:path = [$($fn:literal),*] $(& [$($fm:literal),*])?
+        $(, $var:path = [$($n:literal),*] $(& [$($m:literal),*])?)* $(,)?
+    }) => {
+        #[allow(dead_code)]
+        impl $name {
+            pub const PATTERNS: &'static [$crate::magic_bytes::MagicPattern<{0 $( + 1 + ($fn - $fn) as usize)*}>] = &[
+                $crate::magic_enum!(@pattern [$($fn),*] $([$($fm),*])?),
+                $($crate::magic_enum!(@pattern [$($n),*] $([$($m),*])?),)*
+            ];
+            const VARIANTS: &'static [$t] = &[$fvar, $($var,)*];
+        }
+
+        impl $crate::serde::ByteTypeId<$t> for $name {
+            fn byte_type_id() -> ::std::vec::Vec<&'static str> {
+                <Self as $crate::serde::ByteConstTypeId<$t>>::TYPE_ID.pieces()
+            }
+
+            fn type_descriptor() -> $crate::descriptor::TypeDescriptor {
+                $crate::descriptor::TypeDescriptor::new(
+                    $crate::descriptor::TypeKind::Magic,
+                    "MagicEnum",
+                    vec![$crate::descriptor::TypeParam::Value(concat!(
+                        $crate::magic_enum!(@type_id [$($fn),*] $([$($fm),*])?), ",",
+                        $($crate::magic_enum!(@type_id [$($n),*] $([$($m),*])?), ",",)*
+                    ).into())],
+                ).with_fixed_size(0 $( + 1 + ($fn - $fn) as u64)*)
+            }
+        }
+
+        impl $crate::serde::ByteConstTypeId<$t> for $name {
+            const TYPE_ID: $crate::descriptor::ConstTypeId = $crate::descriptor::ConstTypeId::Str(concat!(
+                "MagicEnum<",
+                $crate::magic_enum!(@type_id [$($fn),*] $([$($fm),*])?), ",",
+                $($crate::magic_enum!(@type_id [$($n),*] $([$($m),*])?), ",",)*
+                ">"
+            ));
+        }
+
+        impl $crate::serde::ByteConstSize<$t> for $name {
+            const BYTE_SIZE:usize = 0 $( + 1 + ($fn - $fn) as usize)*;
+        }
+
+        impl $crate::serde::ByteConstDeserialize<$t> for $name {
+            type ParseErr = $crate::magic_bytes::MagicMismatch<{0 $( + 1 + ($fn - $fn) as usize)*}>;
+
+            fn byte_const_deserialize(io: &[u8; 0 $( + 1 + ($fn - $fn) as usize)*]) -> ::std::result::Result<$t, Self::ParseErr> {
+                for (pattern, variant) in Self::PATTERNS.iter().zip(Self::VARIANTS) {
+                    if pattern.matches(io) {
+                        return ::std::result::Result::Ok(*variant);
+                    }
+                }
+                ::std::result::Result::Err($crate::magic_bytes::MagicMismatch{
+                    expected: [$($fn,)*],
+                    found: *io,
+                    candidates: Self::PATTERNS,
+                })
+            }
+        }
+
+        impl $crate::serde::ByteConstSerialize<$t> for $name {
+            fn byte_const_serialize(item: &$t, io: &mut [u8; 0 $( + 1 + ($fn - $fn) as usize)*]) {
+                *io = match item {
+                    $fvar => [$($fn,)*],
+                    $($var => [$($n,)*],)*
+                };
+            }
+        }
+    };
 }
 
 // Just making sure this compiles
 magic_bytes_type!{
+    #[allow(dead_code)]
     struct AwesomeBytes[0x69, 0x04, 0x20];
+    #[allow(dead_code)]
     struct CoolBytes[b'a', b'b', b'C', 0x00];
 }
 
 pub use magic_bytes_type;
+pub use magic_enum;
 
 #[cfg(test)]
 mod test {
@@ -82,4 +378,34 @@ mod test {
         crate::assert_serde::<AwesomeBytes,_>(&());
         crate::assert_serde::<CoolBytes,_>(&());
     }
+
+    #[test]
+    fn variants() {
+        use crate::serde::ByteConstDeserialize;
+
+        magic_enum!{
+            enum Compression {
+                Gzip = [0x1f, 0x8b, 0x08, 0x00] & [0xff, 0xff, 0xff, 0x00],
+                Zstd = [0x28, 0xb5, 0x2f, 0xfd],
+                Plain = [b'T', b'W', 0x00, 0x00] & [0xff, 0xff, 0xf0, 0x00],
+            }
+            struct CompressionMagic;
+        }
+        crate::assert_serde::<CompressionMagic,_>(&Compression::Gzip);
+        crate::assert_serde::<CompressionMagic,_>(&Compression::Zstd);
+        crate::assert_serde::<CompressionMagic,_>(&Compression::Plain);
+        assert_eq!(CompressionMagic::byte_const_deserialize(&[0x1f, 0x8b, 0x08, 0x42]), Ok(Compression::Gzip));
+        assert_eq!(CompressionMagic::byte_const_deserialize(&[b'T', b'W', 0x0f, 0x42]), Ok(Compression::Plain));
+
+        let err = CompressionMagic::byte_const_deserialize(&[b'T', b'W', 0x10, 0x00]).unwrap_err();
+        assert_eq!(err.expected, [0x1f, 0x8b, 0x08, 0x00]);
+        assert_eq!(err.candidates, CompressionMagic::PATTERNS);
+        assert_eq!(
+            err.to_string(),
+            "unexpected magic bytes [84, 87, 16, 0], expecting one of [31, 139, 8, _], [40, 181, 47, 253], [84, 87, 0&0xf0, _]",
+        );
+        let err = AwesomeBytes::byte_const_deserialize(&[1, 2, 3]).unwrap_err();
+        assert_eq!(err.candidates, [MagicPattern::exact([0x69, 0x04, 0x20])]);
+        assert_eq!(err.to_string(), "unexpected magic bytes [1, 2, 3], expecting [105, 4, 32]");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io as stdio;
+use std::io::{Seek, SeekFrom};
+
+use memmap2::Mmap;
+
+use crate::io::{ByteCursor, ByteRead, EndOfBufferError};
+
+/// A [`ByteCursor`] over a read-only memory map of a file, so reads borrow straight from the page cache.
+#[derive(Debug)]
+pub struct MmapCursor {
+    idx: usize,
+    map: Mmap,
+}
+
+impl MmapCursor {
+    /// Maps all of `file`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or any other, while the cursor or anything borrowed from it is alive. See [`Mmap::map`].
+    pub unsafe fn open(file: &File) -> stdio::Result<Self> {
+        Ok(Self::from_mmap(Mmap::map(file)?))
+    }
+
+    pub fn from_mmap(map: Mmap) -> Self {
+        Self{
+            idx: 0,
+            map,
+        }
+    }
+
+    /// A [`ByteCursor`] over the mapping at the current position
+    fn cursor(&self) -> ByteCursor<'_> {
+        let mut cur = ByteCursor::new(&self.map);
+        cur.set_position(self.idx).unwrap();
+        cur
+    }
+
+    /// Runs `f` on a [`ByteCursor`] at the current position, then moves to wherever it left off
+    fn with_cursor<'s, T>(&'s mut self, f: impl FnOnce(&mut ByteCursor<'s>) -> T) -> T {
+        let mut cur = ByteCursor::new(&self.map);
+        cur.set_position(self.idx).unwrap();
+        let res = f(&mut cur);
+        self.idx = cur.position();
+        res
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.cursor().at_end()
+    }
+
+    /// How many bytes have been consumed so far
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cursor().remaining()
+    }
+
+    pub fn remaining_slice(&self) -> &[u8] {
+        self.cursor().remaining_slice()
+    }
+
+    /// Moves the cursor to `pos`, which may be anywhere up to and including the end of the file
+    pub fn set_position(&mut self, pos: usize) -> Result<(), EndOfBufferError> {
+        self.with_cursor(|cur| cur.set_position(pos))
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), EndOfBufferError> {
+        self.with_cursor(|cur| cur.skip(len))
+    }
+
+    /// Splits off the next `len` bytes as a [`ByteCursor`], and advances this cursor past them.
+    pub fn split_sub_cursor(&mut self, len: usize) -> Result<ByteCursor<'_>, EndOfBufferError> {
+        self.with_cursor(|cur| cur.split_sub_cursor(len))
+    }
+
+    pub fn get_ref(&self) -> &Mmap {
+        &self.map
+    }
+
+    pub fn into_inner(self) -> Mmap {
+        self.map
+    }
+}
+
+impl ByteRead for MmapCursor {
+    type Err = EndOfBufferError;
+
+    fn read_buf<'b>(&'b mut self, len: u64) -> Result<Cow<'b, [u8]>, Self::Err> {
+        let len = usize::try_from(len).map_err(|_| EndOfBufferError)?;
+        self.with_cursor(|cur| cur.split_sub_cursor(len)).map(|sub| sub.remaining_slice().into())
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Err> {
+        self.with_cursor(|cur| cur.read_byte())
+    }
+
+    fn read_up_to<'b>(&'b mut self, max: u64) -> Result<Cow<'b, [u8]>, Self::Err> {
+        let len = max.min(self.remaining() as u64);
+        self.read_buf(len)
+    }
+
+    fn skip_buf(&mut self, len: u64) -> Result<(), Self::Err> {
+        self.with_cursor(|cur| cur.skip_buf(len))
+    }
+}
+
+/// Seeking past the end of the file is an error, unlike for [`File`].
+impl Seek for MmapCursor {
+    fn seek(&mut self, pos: SeekFrom) -> stdio::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => (self.map.len() as u64).checked_add_signed(n),
+            SeekFrom::Current(n) => (self.idx as u64).checked_add_signed(n),
+        };
+        let new = new.and_then(|n| usize::try_from(n).ok()).ok_or_else(|| stdio::Error::new(
+            stdio::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        ))?;
+        self.set_position(new)?;
+        Ok(new as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use crate::serde::{ByteDeserialize, ByteSerialize};
+    use crate::varint::UVarInt;
+    use crate::lists::LengthPrefixList;
+    use crate::string::LengthPrefixString;
+
+    type Strings = LengthPrefixList<UVarInt, LengthPrefixString<UVarInt>>;
+
+    #[test]
+    fn blarg() {
+        let strings = vec!["hello".to_string(), "x".repeat(5000), String::new()];
+        let mut data = vec![];
+        Strings::byte_serialize(&strings, &mut data).unwrap();
+        UVarInt::byte_serialize(&300, &mut data).unwrap();
+
+        let path = std::env::temp_dir().join(format!("twee-mmap-test-{}", std::process::id()));
+        File::create(&path).unwrap().write_all(&data).unwrap();
+        let file = File::open(&path).unwrap();
+        let mut cur = unsafe { MmapCursor::open(&file) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let read:Vec<String> = Strings::byte_deserialize(&mut cur).unwrap();
+        assert_eq!(read, strings);
+        let pos = cur.position();
+        assert_eq!(UVarInt::byte_deserialize(&mut cur), Ok(300));
+        assert!(cur.at_end());
+
+        // reads borrow from the mapping
+        cur.seek(SeekFrom::Start(2)).unwrap();
+        assert!(matches!(cur.read_buf(5).unwrap(), Cow::Borrowed(b"hello")));
+        assert_eq!(cur.seek(SeekFrom::End(-2)).unwrap(), pos as u64);
+        let mut sub = cur.split_sub_cursor(2).unwrap();
+        assert_eq!(UVarInt::byte_deserialize(&mut sub), Ok(300));
+        assert!(cur.seek(SeekFrom::Current(1)).is_err());
+        assert!(cur.seek(SeekFrom::Current(-(data.len() as i64) - 1)).is_err());
+        assert_eq!(cur.read_byte(), Err(EndOfBufferError));
+    }
+}
@@ -0,0 +1,660 @@
+//! Encoders that are values rather than types, so they can carry configuration only known at runtime.
+//!
+//! Every static encoder is also a runtime one, so the two can be mixed freely, as in `DynLengthPrefixList::new(MaxLength::new(UVarInt, 64), DynLengthPrefixString::new(UVarInt))`. The static combinators decode by building their `Dyn` counterpart out of default encoders, so each format is only read in one place.
+//!
+//! Decoding can also carry a context value of the caller's choosing through [`DecodeWith`], so that fields decoded earlier (a version, a flags byte) can decide how later ones are read. The combinators here pass it through untouched, while static encoders drop it; [`Inspect`] and [`When`] are the usual ways to write and read it.
+
+use std::fmt;
+
+use crate::block_list::{BlockCursor, BlockHeader, BlockListError, CountOnly};
+use crate::byte_length::{read_bounded, ByteLengthError};
+use crate::io::{ByteCounter, ByteRead, ByteWrite};
+use crate::const_list::{try_array_from_fn, ParseConstListError};
+use crate::lists::ListParseError;
+use crate::serde::{ByteDeserialize, ByteSerialize, ParseOrIOError};
+use crate::string::StringParseError;
+
+pub trait Encode<T: ?Sized> {
+    fn encode<W: ByteWrite>(&self, item: &T, io: &mut W) -> Result<(), W::Err>;
+
+    fn encoded_size(&self, item: &T) -> u64 {
+        let mut io = ByteCounter::default();
+        self.encode(item, &mut io).unwrap();
+        io.count
+    }
+}
+
+/// Decoding with a mutable context that parent decoders share with their children
+///
+/// Only the combinators in this module hand the context on. Static encoders, including static combinators such as [`ByteLengthPrefixed`][crate::byte_length::ByteLengthPrefixed] and [`BlockList`][crate::block_list::BlockList], decode through the blanket impl and never see it, so a decoder that reads or writes the context has to sit under `Dyn*` combinators all the way up.
+pub trait DecodeWith<T, C: ?Sized> {
+    type ParseErr;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>>;
+
+    /// See [`ByteDeserialize::byte_skip`]
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        self.decode_with(io, ctx).map(drop)
+    }
+}
+
+/// Decoding without a context. Implement [`DecodeWith<T, ()>`] (or anything more general) to get this.
+pub trait Decode<T> : DecodeWith<T, ()> {
+    fn decode<R: ByteRead>(&self, io: &mut R) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>> {
+        self.decode_with(io, &mut ())
+    }
+
+    fn skip<R: ByteRead>(&self, io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        self.skip_with(io, &mut ())
+    }
+}
+
+impl<E, T> Decode<T> for E
+where
+    E: DecodeWith<T, ()>,
+{}
+
+impl<E, T> Encode<T> for E
+where
+    E: ByteSerialize<T>,
+    T: ?Sized,
+{
+    fn encode<W: ByteWrite>(&self, item: &T, io: &mut W) -> Result<(), W::Err> {
+        E::byte_serialize(item, io)
+    }
+
+    fn encoded_size(&self, item: &T) -> u64 {
+        E::size(item)
+    }
+}
+
+/// Static decoders ignore the context, and so do their children
+impl<E, T, C> DecodeWith<T, C> for E
+where
+    E: ByteDeserialize<T>,
+    C: ?Sized,
+{
+    type ParseErr = E::ParseErr;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, _ctx: &mut C) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>> {
+        E::byte_deserialize(io)
+    }
+
+    fn skip_with<R: ByteRead>(&self, io: &mut R, _ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        E::byte_skip(io)
+    }
+}
+
+/// Runtime counterpart of [`crate::lists::LengthPrefixList`], with the same encoding
+#[derive(Debug, Clone, Copy)]
+pub struct DynLengthPrefixList<LE, VE> {
+    pub length: LE,
+    pub item: VE,
+}
+
+impl<LE, VE> DynLengthPrefixList<LE, VE> {
+    pub fn new(length: LE, item: VE) -> Self {
+        Self{length, item}
+    }
+}
+
+impl<LE, VE, V> Encode<[V]> for DynLengthPrefixList<LE, VE>
+where
+    LE: Encode<u64>,
+    VE: Encode<V>,
+{
+    fn encode<W: ByteWrite>(&self, item: &[V], io: &mut W) -> Result<(), W::Err> {
+        self.length.encode(&item.len().try_into().unwrap(), io)?;
+        for el in item {
+            self.item.encode(el, io)?;
+        }
+        Ok(())
+    }
+
+    fn encoded_size(&self, item: &[V]) -> u64 {
+        self.length.encoded_size(&item.len().try_into().unwrap()) + item.iter().map(|el| self.item.encoded_size(el)).sum::<u64>()
+    }
+}
+
+impl<LE, VE, V> Encode<Vec<V>> for DynLengthPrefixList<LE, VE>
+where
+    LE: Encode<u64>,
+    VE: Encode<V>,
+{
+    fn encode<W: ByteWrite>(&self, item: &Vec<V>, io: &mut W) -> Result<(), W::Err> {
+        <Self as Encode<[V]>>::encode(self, item, io)
+    }
+
+    fn encoded_size(&self, item: &Vec<V>) -> u64 {
+        <Self as Encode<[V]>>::encoded_size(self, item)
+    }
+}
+
+impl<LE, VE, V, C> DecodeWith<Vec<V>, C> for DynLengthPrefixList<LE, VE>
+where
+    LE: DecodeWith<u64, C>,
+    VE: DecodeWith<V, C>,
+    C: ?Sized,
+{
+    type ParseErr = ListParseError<LE::ParseErr, VE::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<Vec<V>, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let length:u64 = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+
+        let mut res = Vec::with_capacity(crate::lists::prealloc_len::<V>(length));
+        for idx in 0..length {
+            res.push(self.item.decode_with(io, ctx).map_err(|e| e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}))?);
+        }
+        Ok(res)
+    }
+
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let length:u64 = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(ListParseError::LengthParseError))?;
+        for idx in 0..length {
+            self.item.skip_with(io, ctx).map_err(|e| e.map_parse(|pe| ListParseError::ItemParseError{idx, error: pe}))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runtime counterpart of [`crate::string::LengthPrefixString`], with the same encoding
+#[derive(Debug, Clone, Copy)]
+pub struct DynLengthPrefixString<LE> {
+    pub length: LE,
+}
+
+impl<LE> DynLengthPrefixString<LE> {
+    pub fn new(length: LE) -> Self {
+        Self{length}
+    }
+}
+
+impl<LE> Encode<str> for DynLengthPrefixString<LE>
+where
+    LE: Encode<u64>,
+{
+    fn encode<W: ByteWrite>(&self, item: &str, io: &mut W) -> Result<(), W::Err> {
+        self.length.encode(&item.len().try_into().unwrap(), io)?;
+        io.write_buf(item.as_bytes())
+    }
+
+    fn encoded_size(&self, item: &str) -> u64 {
+        let len:u64 = item.len().try_into().unwrap();
+        self.length.encoded_size(&len) + len
+    }
+}
+
+impl<LE> Encode<String> for DynLengthPrefixString<LE>
+where
+    LE: Encode<u64>,
+{
+    fn encode<W: ByteWrite>(&self, item: &String, io: &mut W) -> Result<(), W::Err> {
+        <Self as Encode<str>>::encode(self, item, io)
+    }
+
+    fn encoded_size(&self, item: &String) -> u64 {
+        <Self as Encode<str>>::encoded_size(self, item)
+    }
+}
+
+impl<LE, C> DecodeWith<String, C> for DynLengthPrefixString<LE>
+where
+    LE: DecodeWith<u64, C>,
+    C: ?Sized,
+{
+    type ParseErr = StringParseError<LE::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<String, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len:u64 = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(StringParseError::LengthParseError))?;
+        let buf = io.read_buf(len).map_err(ParseOrIOError::IO)?;
+        String::from_utf8(buf.into_owned()).map_err(StringParseError::InvalidUtf8OwnedError).map_err(ParseOrIOError::Parse)
+    }
+
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len:u64 = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(StringParseError::LengthParseError))?;
+        io.skip_buf(len).map_err(ParseOrIOError::IO)
+    }
+}
+
+/// Runtime counterpart of [`crate::const_list::ConstListEncoder`], with the same encoding
+#[derive(Debug, Clone, Copy)]
+pub struct DynConstListEncoder<VE> {
+    pub item: VE,
+}
+
+impl<VE> DynConstListEncoder<VE> {
+    pub fn new(item: VE) -> Self {
+        Self{item}
+    }
+}
+
+impl<VE, V, const N: usize> Encode<[V; N]> for DynConstListEncoder<VE>
+where
+    VE: Encode<V>,
+{
+    fn encode<W: ByteWrite>(&self, item: &[V; N], io: &mut W) -> Result<(), W::Err> {
+        for el in item {
+            self.item.encode(el, io)?;
+        }
+        Ok(())
+    }
+
+    fn encoded_size(&self, item: &[V; N]) -> u64 {
+        item.iter().map(|el| self.item.encoded_size(el)).sum()
+    }
+}
+
+impl<VE, V, C, const N: usize> DecodeWith<[V; N], C> for DynConstListEncoder<VE>
+where
+    VE: DecodeWith<V, C>,
+    C: ?Sized,
+{
+    type ParseErr = ParseConstListError<VE::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<[V; N], ParseOrIOError<Self::ParseErr, R::Err>> {
+        try_array_from_fn(|index| self.item.decode_with(io, ctx).map_err(|error| error.map_parse(|e| ParseConstListError{index, error: e})))
+    }
+
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        for index in 0..N {
+            self.item.skip_with(io, ctx).map_err(|error| error.map_parse(|e| ParseConstListError{index, error: e}))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runtime counterpart of [`crate::byte_length::ByteLengthPrefixed`], with the same encoding. `inner` is decoded from a reader bounded to the prefixed length.
+#[derive(Debug, Clone, Copy)]
+pub struct DynByteLengthPrefixed<LE, E> {
+    pub length: LE,
+    pub inner: E,
+}
+
+impl<LE, E> DynByteLengthPrefixed<LE, E> {
+    pub fn new(length: LE, inner: E) -> Self {
+        Self{length, inner}
+    }
+}
+
+impl<LE, E, T> Encode<T> for DynByteLengthPrefixed<LE, E>
+where
+    LE: Encode<u64>,
+    E: Encode<T>,
+    T: ?Sized,
+{
+    fn encode<W: ByteWrite>(&self, item: &T, io: &mut W) -> Result<(), W::Err> {
+        self.length.encode(&self.inner.encoded_size(item), io)?;
+        self.inner.encode(item, io)
+    }
+
+    fn encoded_size(&self, item: &T) -> u64 {
+        let len = self.inner.encoded_size(item);
+        self.length.encoded_size(&len) + len
+    }
+}
+
+impl<LE, E, T, C> DecodeWith<T, C> for DynByteLengthPrefixed<LE, E>
+where
+    LE: DecodeWith<u64, C>,
+    E: DecodeWith<T, C>,
+    C: ?Sized,
+{
+    type ParseErr = ByteLengthError<LE::ParseErr, E::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(ByteLengthError::LengthParseError))?;
+        read_bounded(io, len, |io| self.inner.decode_with(io, ctx))
+    }
+
+    /// Skips the whole value using its length, without looking inside it.
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len = self.length.decode_with(io, ctx).map_err(|e| e.map_parse(ByteLengthError::LengthParseError))?;
+        io.skip_buf(len).map_err(ParseOrIOError::IO)
+    }
+}
+
+/// Runtime counterpart of [`crate::block_list::BlockList`], with the same encoding. Everything is written as a single block.
+#[derive(Debug, Clone, Copy)]
+pub struct DynBlockList<LE, VE, B = CountOnly> {
+    pub length: LE,
+    pub item: VE,
+    pub header: B,
+}
+
+impl<LE, VE, B> DynBlockList<LE, VE, B> {
+    pub fn new(length: LE, item: VE, header: B) -> Self {
+        Self{length, item, header}
+    }
+}
+
+impl<LE, VE, B, V> Encode<[V]> for DynBlockList<LE, VE, B>
+where
+    LE: Encode<u64>,
+    VE: Encode<V>,
+    B: BlockHeader,
+{
+    fn encode<W: ByteWrite>(&self, item: &[V], io: &mut W) -> Result<(), W::Err> {
+        if !item.is_empty() {
+            self.length.encode(&(item.len() as u64), io)?;
+            if B::HAS_SIZE {
+                self.length.encode(&item.iter().map(|el| self.item.encoded_size(el)).sum(), io)?;
+            }
+            for el in item {
+                self.item.encode(el, io)?;
+            }
+        }
+        self.length.encode(&0, io)
+    }
+}
+
+impl<LE, VE, B, V> Encode<Vec<V>> for DynBlockList<LE, VE, B>
+where
+    LE: Encode<u64>,
+    VE: Encode<V>,
+    B: BlockHeader,
+{
+    fn encode<W: ByteWrite>(&self, item: &Vec<V>, io: &mut W) -> Result<(), W::Err> {
+        <Self as Encode<[V]>>::encode(self, item, io)
+    }
+}
+
+impl<LE, VE, B, V, C> DecodeWith<Vec<V>, C> for DynBlockList<LE, VE, B>
+where
+    LE: DecodeWith<u64, C>,
+    VE: DecodeWith<V, C>,
+    B: BlockHeader,
+    C: ?Sized,
+{
+    type ParseErr = BlockListError<LE::ParseErr, VE::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<Vec<V>, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let mut cursor = BlockCursor::new(B::HAS_SIZE);
+        let mut res = Vec::new();
+        while cursor.start_block(io, &self.length, ctx)? {
+            while cursor.block_left() > 0 {
+                res.push(cursor.read_item(io, |io| self.item.decode_with(io, ctx))?);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Skips sized blocks whole, without looking at their items.
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        BlockCursor::new(B::HAS_SIZE).skip_to_end(io, &self.length, ctx, false, |io, ctx| self.item.skip_with(io, ctx))
+    }
+}
+
+/// Decodes a length with `inner` and rejects it if it's over `max`. Encoding doesn't check, so writers stay infallible.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxLength<E> {
+    pub inner: E,
+    pub max: u64,
+}
+
+impl<E> MaxLength<E> {
+    pub fn new(inner: E, max: u64) -> Self {
+        Self{inner, max}
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MaxLengthError<P> {
+    Parse(P),
+    TooLong{len: u64, max: u64},
+}
+
+impl<P: fmt::Display> fmt::Display for MaxLengthError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => e.fmt(f),
+            Self::TooLong{len, max} => write!(f, "length {} is over the maximum of {}", len, max),
+        }
+    }
+}
+
+impl<P: std::error::Error + 'static> std::error::Error for MaxLengthError<P> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e),
+            Self::TooLong{..} => None,
+        }
+    }
+}
+
+impl<E: Encode<u64>> Encode<u64> for MaxLength<E> {
+    fn encode<W: ByteWrite>(&self, item: &u64, io: &mut W) -> Result<(), W::Err> {
+        self.inner.encode(item, io)
+    }
+
+    fn encoded_size(&self, item: &u64) -> u64 {
+        self.inner.encoded_size(item)
+    }
+}
+
+impl<E, C> DecodeWith<u64, C> for MaxLength<E>
+where
+    E: DecodeWith<u64, C>,
+    C: ?Sized,
+{
+    type ParseErr = MaxLengthError<E::ParseErr>;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<u64, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len = self.inner.decode_with(io, ctx).map_err(|e| e.map_parse(MaxLengthError::Parse))?;
+        if len > self.max {
+            return Err(ParseOrIOError::Parse(MaxLengthError::TooLong{len, max: self.max}));
+        }
+        Ok(len)
+    }
+}
+
+/// Decodes with `inner`, then hands the value to `f` so it can update the context
+#[derive(Debug, Clone, Copy)]
+pub struct Inspect<E, F> {
+    pub inner: E,
+    pub f: F,
+}
+
+impl<E, F> Inspect<E, F> {
+    pub fn new(inner: E, f: F) -> Self {
+        Self{inner, f}
+    }
+}
+
+impl<E, F, T> Encode<T> for Inspect<E, F>
+where
+    E: Encode<T>,
+    T: ?Sized,
+{
+    fn encode<W: ByteWrite>(&self, item: &T, io: &mut W) -> Result<(), W::Err> {
+        self.inner.encode(item, io)
+    }
+
+    fn encoded_size(&self, item: &T) -> u64 {
+        self.inner.encoded_size(item)
+    }
+}
+
+impl<E, F, T, C> DecodeWith<T, C> for Inspect<E, F>
+where
+    E: DecodeWith<T, C>,
+    F: Fn(&T, &mut C),
+    C: ?Sized,
+{
+    type ParseErr = E::ParseErr;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>> {
+        let value = self.inner.decode_with(io, ctx)?;
+        (self.f)(&value, ctx);
+        Ok(value)
+    }
+}
+
+/// A value that is only present when `present` says so for the current context. Nothing is written for `None`; it's up to the caller to only have `Some` when the context will agree.
+#[derive(Debug, Clone, Copy)]
+pub struct When<E, F> {
+    pub inner: E,
+    pub present: F,
+}
+
+impl<E, F> When<E, F> {
+    pub fn new(inner: E, present: F) -> Self {
+        Self{inner, present}
+    }
+}
+
+impl<E, F, T> Encode<Option<T>> for When<E, F>
+where
+    E: Encode<T>,
+{
+    fn encode<W: ByteWrite>(&self, item: &Option<T>, io: &mut W) -> Result<(), W::Err> {
+        match item {
+            Some(v) => self.inner.encode(v, io),
+            None => Ok(()),
+        }
+    }
+
+    fn encoded_size(&self, item: &Option<T>) -> u64 {
+        item.as_ref().map_or(0, |v| self.inner.encoded_size(v))
+    }
+}
+
+impl<E, F, T, C> DecodeWith<Option<T>, C> for When<E, F>
+where
+    E: DecodeWith<T, C>,
+    F: Fn(&C) -> bool,
+    C: ?Sized,
+{
+    type ParseErr = E::ParseErr;
+
+    fn decode_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<Option<T>, ParseOrIOError<Self::ParseErr, R::Err>> {
+        if (self.present)(ctx) {
+            self.inner.decode_with(io, ctx).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn skip_with<R: ByteRead>(&self, io: &mut R, ctx: &mut C) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        if (self.present)(ctx) {
+            self.inner.skip_with(io, ctx)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::ByteCursor;
+    use crate::varint::UVarInt;
+    use crate::endians::NumberEncodingBE as BE;
+
+    #[test]
+    fn blarg() {
+        let strings = vec!["hello".to_string(), String::new(), "x".repeat(300)];
+        let enc = DynLengthPrefixList::new(UVarInt, DynLengthPrefixString::new(UVarInt));
+
+        // same bytes as the static encoders
+        let mut data = Vec::new();
+        enc.encode(&strings, &mut data).unwrap();
+        let mut expected = Vec::new();
+        crate::lists::LengthPrefixList::<UVarInt, crate::string::LengthPrefixString<UVarInt>>::byte_serialize(&strings, &mut expected).unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(enc.encoded_size(&strings), data.len() as u64);
+
+        let read:Vec<String> = enc.decode(&mut ByteCursor::new(&data)).unwrap();
+        assert_eq!(read, strings);
+
+        let mut cur = ByteCursor::new(&data);
+        Decode::<Vec<String>>::skip(&enc, &mut cur).unwrap();
+        assert!(cur.at_end());
+
+        let arrs = DynConstListEncoder::new(BE);
+        let mut data = Vec::new();
+        arrs.encode(&[1u16, 2, 3], &mut data).unwrap();
+        let read:[u16; 3] = arrs.decode(&mut ByteCursor::new(&data)).unwrap();
+        assert_eq!(read, [1, 2, 3]);
+    }
+
+    #[test]
+    fn bounded_and_blocks() {
+        use crate::block_list::{BlockList, CountAndSize};
+        use crate::byte_length::ByteLengthPrefixed;
+        type Str = crate::string::LengthPrefixString<UVarInt>;
+
+        let strings = vec!["hello".to_string(), String::new(), "x".repeat(300)];
+        let bounded = DynByteLengthPrefixed::new(BE, DynLengthPrefixList::new(UVarInt, DynLengthPrefixString::new(UVarInt)));
+        let mut data = Vec::new();
+        bounded.encode(&strings, &mut data).unwrap();
+        let mut expected = Vec::new();
+        ByteLengthPrefixed::<BE, crate::lists::LengthPrefixList<UVarInt, Str>>::byte_serialize(&strings, &mut expected).unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(bounded.encoded_size(&strings), data.len() as u64);
+        assert_eq!(bounded.decode(&mut ByteCursor::new(&data)), Ok(strings.clone()));
+
+        let blocks = DynBlockList::new(UVarInt, DynLengthPrefixString::new(UVarInt), CountAndSize);
+        let mut data = Vec::new();
+        blocks.encode(&strings, &mut data).unwrap();
+        let mut expected = Vec::new();
+        BlockList::<UVarInt, Str, CountAndSize>::byte_serialize(&strings, &mut expected).unwrap();
+        assert_eq!(data, expected);
+        assert_eq!(blocks.decode(&mut ByteCursor::new(&data)), Ok(strings));
+
+        data.push(0xaa);
+        let mut cur = ByteCursor::new(&data);
+        Decode::<Vec<String>>::skip(&blocks, &mut cur).unwrap();
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+    }
+
+    #[test]
+    fn max_length() {
+        let strings = vec!["x".repeat(10); 3];
+        let mut data = Vec::new();
+        DynLengthPrefixList::new(UVarInt, DynLengthPrefixString::new(UVarInt)).encode(&strings, &mut data).unwrap();
+
+        let roomy = DynLengthPrefixList::new(MaxLength::new(UVarInt, 3), DynLengthPrefixString::new(MaxLength::new(UVarInt, 10)));
+        assert_eq!(roomy.decode(&mut ByteCursor::new(&data)), Ok(strings));
+
+        let tight = DynLengthPrefixList::new(UVarInt, DynLengthPrefixString::new(MaxLength::new(UVarInt, 9)));
+        let res:Result<Vec<String>, _> = tight.decode(&mut ByteCursor::new(&data));
+        assert!(matches!(res, Err(ParseOrIOError::Parse(ListParseError::ItemParseError{
+            idx: 0,
+            error: StringParseError::LengthParseError(MaxLengthError::TooLong{len: 10, max: 9}),
+        }))));
+    }
+
+    #[test]
+    fn context() {
+        #[derive(Default)]
+        struct Ctx {
+            version: u8,
+        }
+        let version = Inspect::new(BE, |v:&u8, ctx:&mut Ctx| ctx.version = *v);
+        let extra = DynLengthPrefixList::new(UVarInt, When::new(BE, |ctx:&Ctx| ctx.version >= 3));
+
+        for (v, item) in [(2u8, None), (3, Some(7u32))] {
+            let mut data = Vec::new();
+            version.encode(&v, &mut data).unwrap();
+            extra.encode(&vec![item; 2], &mut data).unwrap();
+
+            let mut ctx = Ctx::default();
+            let mut cur = ByteCursor::new(&data);
+            assert_eq!(version.decode_with(&mut cur, &mut ctx), Ok(v));
+            let read:Vec<Option<u32>> = extra.decode_with(&mut cur, &mut ctx).unwrap();
+            assert_eq!(read, vec![item; 2]);
+            assert!(cur.at_end());
+        }
+
+        // reaches items through a byte length and blocks too
+        let nested = DynByteLengthPrefixed::new(BE, DynBlockList::new(UVarInt, When::new(BE, |ctx:&Ctx| ctx.version >= 3), CountOnly));
+        let items = vec![Some(7u32); 3];
+        let mut data = Vec::new();
+        nested.encode(&items, &mut data).unwrap();
+        let mut ctx = Ctx{version: 3};
+        assert_eq!(nested.decode_with(&mut ByteCursor::new(&data), &mut ctx), Ok(items));
+        let mut ctx = Ctx{version: 2};
+        let res:Result<Vec<Option<u32>>, _> = nested.decode_with(&mut ByteCursor::new(&data), &mut ctx);
+        assert!(matches!(res, Err(ParseOrIOError::Parse(ByteLengthError::TrailingBytes{..}))));
+    }
+}
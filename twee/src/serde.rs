@@ -2,6 +2,7 @@ use std::fmt::{self, Display};
 use std::error::Error;
 
 use super::io;
+use crate::descriptor::{ConstTypeId, TypeDescriptor};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ParseOrIOError<P, I> {
@@ -59,8 +60,22 @@ impl<P, I> ParseOrIOError<P, I> {
 
 /// Types that implement this trait should be unit structs or structs with only [`PhantomData`][`std::marker::PhantomData`]-type members. This is encouraged through the `Default` and `Copy` requirements
 pub trait ByteTypeId<T: ?Sized>: Default + Copy {
-    /// This *must* always give the same value; This is not an associated const because of rust limitations.
+    /// This *must* always give the same value; This is not an associated const because of rust limitations, but see [`ByteConstTypeId`].
     fn byte_type_id() -> Vec<&'static str>;
+
+    /// The same information as [`ByteTypeId::byte_type_id`], as a tree. By default the whole type id becomes a single [`TypeKind::Opaque`][crate::descriptor::TypeKind::Opaque] node.
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::opaque(Self::byte_type_id().concat())
+    }
+}
+
+/// A [`ByteTypeId`] that is known at compile time, for hashing without allocating. `TYPE_ID` must write out the same string as [`ByteTypeId::byte_type_id`].
+///
+/// Encoders without type parameters should implement `byte_type_id` as [`TYPE_ID.pieces()`][ConstTypeId::pieces] so the string is only spelled out once. Combinators can't, since they also give a type id for children that are only [`ByteTypeId`].
+pub trait ByteConstTypeId<T: ?Sized> : ByteTypeId<T> {
+    const TYPE_ID: ConstTypeId;
+    /// The 64-bit FNV-1a hash of the type id string
+    const TYPE_FINGERPRINT: u64 = Self::TYPE_ID.fingerprint();
 }
 
 pub trait ByteDeserialize<T> : ByteTypeId<T> {
@@ -68,12 +83,36 @@ pub trait ByteDeserialize<T> : ByteTypeId<T> {
 
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<T, ParseOrIOError<Self::ParseErr, R::Err>>;
 
+    /// Decodes into `place`, reusing any allocations it already owns.
+    ///
+    /// After a failure `place` still holds a valid value, but which one depends on the implementation. The default implementation decodes normally and assigns, so it leaves `place` untouched on failure.
+    fn byte_deserialize_in_place<R: io::ByteRead>(io: &mut R, place: &mut T) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        *place = Self::byte_deserialize(io)?;
+        Ok(())
+    }
+
+    /// Reads past a value without building it. This only has to find where the value ends, so it may accept input that [`ByteDeserialize::byte_deserialize`] would reject.
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        Self::byte_deserialize(io).map(drop)
+    }
+
+    /// Reads past a value without building it, failing exactly when [`ByteDeserialize::byte_deserialize`] would.
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        Self::byte_deserialize(io).map(drop)
+    }
+
+    /// How many bytes every value takes, if that's always the same. Skipping relies on this being exact.
     fn guess_size() -> Option<usize> { None }
 }
 
 pub trait ByteSerialize<T: ?Sized> : ByteTypeId<T> {
     fn byte_serialize<W: io::ByteWrite>(item: &T, io: &mut W) -> Result<(), W::Err>;
 
+    /// Like [`ByteSerialize::byte_serialize`], but free to go back and patch what it wrote instead of working out sizes up front. Combinators should forward this to their inner encoders.
+    fn byte_serialize_seekable<W: io::SeekableByteWrite>(item: &T, io: &mut W) -> Result<(), W::Err> {
+        Self::byte_serialize(item, io)
+    }
+
     fn size(item: &T) -> u64 {
         let mut io = io::ByteCounter::default();
         Self::byte_serialize(item, &mut io).unwrap();
@@ -102,6 +141,10 @@ where P: ByteConstDeserialize<T>, [(); Self::BYTE_SIZE]: {
         Self::byte_const_deserialize(buf_arr).map_err(ParseOrIOError::Parse)
     }
 
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        io.skip_buf(Self::BYTE_SIZE.try_into().unwrap()).map_err(ParseOrIOError::IO)
+    }
+
     fn guess_size() -> Option<usize> {
         Some(Self::BYTE_SIZE)
     }
@@ -2,7 +2,9 @@ use std::marker::PhantomData;
 use std::borrow::Cow;
 
 use crate::io;
-use crate::serde::{ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
+use crate::runtime::{Decode, DynLengthPrefixString, Encode};
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind, TypeParam};
+use crate::serde::{ByteConstTypeId, ByteTypeId, ByteDeserialize, ByteSerialize, ParseOrIOError};
 
 #[derive(Default, Copy, Clone)]
 pub struct LengthPrefixString<LE>
@@ -12,13 +14,33 @@ where
     length_encoder: PhantomData<LE>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StringParseError<L> {
     LengthParseError(L),
     InvalidUtf8Error(std::str::Utf8Error),
     InvalidUtf8OwnedError(std::string::FromUtf8Error),
 }
 
+impl<L: std::fmt::Display> std::fmt::Display for StringParseError<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LengthParseError(e) => write!(f, "invalid string length: {}", e),
+            Self::InvalidUtf8Error(e) => write!(f, "invalid string: {}", e),
+            Self::InvalidUtf8OwnedError(e) => write!(f, "invalid string: {}", e),
+        }
+    }
+}
+
+impl<L: std::error::Error + 'static> std::error::Error for StringParseError<L> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::LengthParseError(e) => Some(e),
+            Self::InvalidUtf8Error(e) => Some(e),
+            Self::InvalidUtf8OwnedError(e) => Some(e),
+        }
+    }
+}
+
 impl<L> From<L> for StringParseError<L> {
     fn from(l: L) -> Self {
         StringParseError::LengthParseError(l)
@@ -36,6 +58,35 @@ where
         res.push(">");
         res
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::new(TypeKind::String, "twee::LengthPrefixedString", vec![TypeParam::Type(LE::type_descriptor())])
+    }
+}
+
+impl<LE> ByteConstTypeId<str> for LengthPrefixString<LE>
+where
+    LE: ByteConstTypeId<u64>,
+{
+    const TYPE_ID: ConstTypeId = ConstTypeId::Seq(&[
+        ConstTypeId::Str("twee::LengthPrefixedString<"),
+        LE::TYPE_ID,
+        ConstTypeId::Str(">"),
+    ]);
+}
+
+impl<LE> ByteConstTypeId<String> for LengthPrefixString<LE>
+where
+    LE: ByteConstTypeId<u64>,
+{
+    const TYPE_ID: ConstTypeId = <Self as ByteConstTypeId<str>>::TYPE_ID;
+}
+
+impl<LE> ByteConstTypeId<Cow<'_, str>> for LengthPrefixString<LE>
+where
+    LE: ByteConstTypeId<u64>,
+{
+    const TYPE_ID: ConstTypeId = <Self as ByteConstTypeId<str>>::TYPE_ID;
 }
 
 impl<LE> ByteTypeId<String> for LengthPrefixString<LE>
@@ -45,6 +96,10 @@ where
     fn byte_type_id() -> Vec<&'static str> {
         <Self as ByteTypeId<str>>::byte_type_id()
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        <Self as ByteTypeId<str>>::type_descriptor()
+    }
 }
 
 impl<LE> ByteTypeId<Cow<'_, str>> for LengthPrefixString<LE>
@@ -54,6 +109,10 @@ where
     fn byte_type_id() -> Vec<&'static str> {
         <Self as ByteTypeId<str>>::byte_type_id()
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        <Self as ByteTypeId<str>>::type_descriptor()
+    }
 }
 
 impl<LE> ByteSerialize<str> for LengthPrefixString<LE>
@@ -61,14 +120,24 @@ where
     LE: ByteSerialize<u64>,
 {
     fn byte_serialize<W: io::ByteWrite>(item: &str, io: &mut W) -> Result<(), W::Err> {
-        let len:u64 = item.len().try_into().unwrap();
-        LE::byte_serialize(&len, io)?;
-        io.write_buf(item.as_bytes())
+        DynLengthPrefixString::new(LE::default()).encode(item, io)
     }
 
     fn size(item: &str) -> u64 {
-        let len:u64 = item.len().try_into().unwrap();
-        LE::size(&len) + len
+        DynLengthPrefixString::new(LE::default()).encoded_size(item)
+    }
+}
+
+impl<LE> ByteSerialize<String> for LengthPrefixString<LE>
+where
+    LE: ByteSerialize<u64>,
+{
+    fn byte_serialize<W: io::ByteWrite>(item: &String, io: &mut W) -> Result<(), W::Err> {
+        <Self as ByteSerialize<str>>::byte_serialize(item, io)
+    }
+
+    fn size(item: &String) -> u64 {
+        <Self as ByteSerialize<str>>::size(item)
     }
 }
 
@@ -79,12 +148,39 @@ where
     type ParseErr = StringParseError<LE::ParseErr>;
 
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<String, ParseOrIOError<Self::ParseErr, R::Err>> {
+        DynLengthPrefixString::new(LE::default()).decode(io)
+    }
+
+    /// Leaves `place` empty on failure.
+    fn byte_deserialize_in_place<R: io::ByteRead>(io: &mut R, place: &mut String) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        place.clear();
         let len:u64 = LE::byte_deserialize(io).map_err(|e| e.map_parse(StringParseError::LengthParseError))?;
-        let mut horror = Vec::with_capacity(len.try_into().unwrap());
-        for _ in 0..len {
-            horror.push(io.read_byte().map_err(ParseOrIOError::IO)?);
+        let buf = io.read_buf(len).map_err(ParseOrIOError::IO)?;
+        match std::str::from_utf8(&buf) {
+            Ok(s) => {
+                place.push_str(s);
+                Ok(())
+            },
+            Err(_) => {
+                let e = String::from_utf8(buf.into_owned()).unwrap_err();
+                Err(ParseOrIOError::Parse(StringParseError::InvalidUtf8OwnedError(e)))
+            },
         }
-        String::from_utf8(horror).map_err(StringParseError::InvalidUtf8OwnedError).map_err(ParseOrIOError::Parse)
+    }
+
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        Decode::<String>::skip(&DynLengthPrefixString::new(LE::default()), io)
+    }
+
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        let len:u64 = LE::byte_deserialize(io).map_err(|e| e.map_parse(StringParseError::LengthParseError))?;
+        let buf = io.read_buf(len).map_err(ParseOrIOError::IO)?;
+        if std::str::from_utf8(&buf).is_ok() {
+            return Ok(());
+        }
+        // only allocate to build the same error that decoding gives
+        let e = String::from_utf8(buf.into_owned()).unwrap_err();
+        Err(ParseOrIOError::Parse(StringParseError::InvalidUtf8OwnedError(e)))
     }
 }
 
@@ -101,4 +197,25 @@ mod test {
             String,
         >(a);
     }
+
+    #[test]
+    fn skip_and_validate() {
+        use crate::varint::UVarInt;
+        type Str = LengthPrefixString<UVarInt>;
+
+        let good = [3, b'a', b'b', b'c', 0xaa];
+        let mut cur = io::ByteCursor::new(&good);
+        Str::byte_skip(&mut cur).unwrap();
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+        let mut cur = io::ByteCursor::new(&good);
+        Str::byte_validate(&mut cur).unwrap();
+        assert_eq!(cur.remaining_slice(), &[0xaa]);
+
+        // skipping only looks at the length, validating gives the same error as decoding
+        let bad = [2, b'a', 0xff];
+        assert!(Str::byte_skip(&mut io::ByteCursor::new(&bad)).is_ok());
+        let decoded = Str::byte_deserialize(&mut io::ByteCursor::new(&bad)).unwrap_err();
+        assert_eq!(Str::byte_validate(&mut io::ByteCursor::new(&bad)), Err(decoded));
+        assert!(Str::byte_skip(&mut io::ByteCursor::new(&[5, b'a'])).is_err());
+    }
 }
\ No newline at end of file
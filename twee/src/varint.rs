@@ -1,5 +1,6 @@
-use crate::serde::{ByteSerialize, ByteDeserialize, ByteTypeId, ParseOrIOError};
+use crate::serde::{ByteConstTypeId, ByteSerialize, ByteDeserialize, ByteTypeId, ParseOrIOError};
 use crate::io;
+use crate::descriptor::{ConstTypeId, TypeDescriptor, TypeKind};
 
 #[derive(Debug,Copy,Clone,Default)]
 pub struct UVarInt;
@@ -7,10 +8,26 @@ pub struct UVarInt;
 #[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
 pub struct VarIntTooBig;
 
+impl std::fmt::Display for VarIntTooBig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "varint does not fit in 64 bits")
+    }
+}
+
+impl std::error::Error for VarIntTooBig {}
+
 impl ByteTypeId<u64> for UVarInt {
     fn byte_type_id() -> Vec<&'static str> {
-        vec!["twee::UVarInt"]
+        <Self as ByteConstTypeId<u64>>::TYPE_ID.pieces()
     }
+
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::leaf(TypeKind::VarInt, "twee::UVarInt")
+    }
+}
+
+impl ByteConstTypeId<u64> for UVarInt {
+    const TYPE_ID: ConstTypeId = ConstTypeId::Str("twee::UVarInt");
 }
 
 impl ByteSerialize<u64> for UVarInt {
@@ -43,9 +60,31 @@ impl ByteSerialize<u64> for UVarInt {
     }
 }
 
+/// Reads past a varint, checking the same limit as decoding it
+fn skip_varint<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<VarIntTooBig, R::Err>> {
+    for cnt in 1..=10 {
+        let byte = io.read_byte().map_err(ParseOrIOError::IO)?;
+        if cnt == 10 && byte > 1 {
+            break;
+        }
+        if byte <= 127 {
+            return Ok(());
+        }
+    }
+    Err(ParseOrIOError::Parse(VarIntTooBig))
+}
+
 impl ByteDeserialize<u64> for UVarInt {
     type ParseErr = VarIntTooBig;
 
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        skip_varint(io)
+    }
+
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        skip_varint(io)
+    }
+
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<u64, ParseOrIOError<Self::ParseErr, R::Err>> {
         let mut val = 0u64;
         let mut cnt = 1;
@@ -65,7 +104,7 @@ impl ByteDeserialize<u64> for UVarInt {
             }
             cnt += 1;
         }
-        Ok(val.into())
+        Ok(val)
     }
 }
 
@@ -76,10 +115,18 @@ pub struct SVarInt;
 
 impl ByteTypeId<i64> for SVarInt {
     fn byte_type_id() -> Vec<&'static str> {
-        vec!["twee::SVarInt"]
+        <Self as ByteConstTypeId<i64>>::TYPE_ID.pieces()
+    }
+
+    fn type_descriptor() -> TypeDescriptor {
+        TypeDescriptor::leaf(TypeKind::VarInt, "twee::SVarInt")
     }
 }
 
+impl ByteConstTypeId<i64> for SVarInt {
+    const TYPE_ID: ConstTypeId = ConstTypeId::Str("twee::SVarInt");
+}
+
 // Encoding a signed varint is just taking the number and encoding `(n << 1) ^ (n >> un::BITS-1)`
 
 fn encode_svarint(n: i64) -> u64 {
@@ -106,6 +153,14 @@ impl ByteDeserialize<i64> for SVarInt {
     fn byte_deserialize<R: io::ByteRead>(io: &mut R) -> Result<i64, ParseOrIOError<Self::ParseErr, R::Err>> {
         UVarInt::byte_deserialize(io).map(decode_svarint)
     }
+
+    fn byte_skip<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        skip_varint(io)
+    }
+
+    fn byte_validate<R: io::ByteRead>(io: &mut R) -> Result<(), ParseOrIOError<Self::ParseErr, R::Err>> {
+        skip_varint(io)
+    }
 }
 
 #[cfg(test)]
@@ -152,6 +207,28 @@ mod test {
         assert_uvarint_serde(18446744073709551615);
     }
 
+    #[test]
+    fn skip_matches_decode() {
+        let inputs:[&[u8]; 6] = [
+            &[0x00, 0xaa],
+            &[0xff, 0x01, 0xaa],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0xaa],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02, 0xaa],
+            &[0xff; 12],
+            &[0x80, 0x80],
+        ];
+        for input in inputs {
+            let mut decoded = io::ByteCursor::new(input);
+            let mut skipped = io::ByteCursor::new(input);
+            let mut validated = io::ByteCursor::new(input);
+            let res = UVarInt::byte_deserialize(&mut decoded).map(drop);
+            assert_eq!(UVarInt::byte_skip(&mut skipped), res, "{:?}", input);
+            assert_eq!(UVarInt::byte_validate(&mut validated), res, "{:?}", input);
+            assert_eq!(skipped.position(), decoded.position(), "{:?}", input);
+            assert_eq!(validated.position(), decoded.position(), "{:?}", input);
+        }
+    }
+
     #[test]
     fn protobuf_examples() {
         assert_eq!(encode_svarint(0), 0);